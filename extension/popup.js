@@ -0,0 +1,10 @@
+import init, { run_popup } from "./pkg/wasm.js";
+
+// host:port of the OpenMemex server, keep in sync with host_permissions in manifest.json
+const SERVER = "localhost:3000";
+
+chrome.tabs.query({ active: true, currentWindow: true }, async (tabs) => {
+  const tab = tabs[0] || {};
+  await init();
+  run_popup(SERVER, tab.url || "", tab.title || "");
+});
@@ -1,18 +1,17 @@
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use wasm_bindgen::prelude::*;
+use url::Url;
+use urlencoding::encode;
+use yew::agent::{Dispatched, Dispatcher};
+use yew::format::{Json, Nothing};
+use yew::prelude::*;
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    utils::*,
-};
+use yew::utils::host;
+use yew_router::prelude::*;
 use crate::api::*;
+use crate::app_router::*;
+use crate::canonical::*;
+use crate::sync::*;
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct NoteResponse {
-    pub code: i64,
-}
+pub type Link = RouterAnchor<AppRoute>;
 
 #[derive(Debug)]
 pub enum AddNoteMsg {
@@ -24,7 +23,11 @@ pub enum AddNoteMsg {
     TagKeyDown(KeyboardEvent),
     AddTag(String),
 
-    SubmitResponse(Result<Vec<NoteResponse>, anyhow::Error>),
+    // duplicate check, tagged with the canonical url it was made for
+    ReceiveDuplicates(String, Result<Vec<Cache>, anyhow::Error>),
+    OpenDuplicate,
+    TagDuplicate,
+    CaptureAnyway,
 }
 
 pub struct AddNote {
@@ -32,33 +35,151 @@ pub struct AddNote {
     tag: String,     // holds text in the tag input
     tags: Vec<String>,
     link: ComponentLink<Self>,
-    submit_task: Option<FetchTask>,
+    sync: Dispatcher<SyncAgent>,
+    props: Props,
+    // canonical form of the url in the note, if it is one
+    canonical: Option<String>,
+    duplicate: Option<Cache>,
+    duplicate_task: Option<FetchTask>,
+    status: Option<String>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    #[prop_or_default]
+    pub prefill: Option<AddNotePayload>,
+    #[prop_or_default]
+    pub open_entry_callback: Callback<Cache>,
+}
+
+// Reads a note shared from another app through the manifest share_target,
+// which opens /frontend/addnote?title=...&text=...&url=...
+pub fn share_target_payload() -> Option<AddNotePayload> {
+    // many share sheets put the link in `text` rather than `url`
+    let text = query_param("text");
+    let text_url = text.clone().filter(|t| Url::parse(t).is_ok());
+    let content = query_param("url")
+        .or(text_url)
+        .or(text)
+        .or_else(|| query_param("title"))?;
+    Some(AddNotePayload {
+        note_content: content,
+        tags: vec![],
+    })
+}
+
+// Drag-to-bookmarks link that opens the capture route for the current page
+fn bookmarklet() -> String {
+    format!(
+        "javascript:(function(){{window.open('http://{}/frontend/capture?url='+encodeURIComponent(location.href)\
+         +'&title='+encodeURIComponent(document.title)\
+         +'&selection='+encodeURIComponent(window.getSelection().toString()),\
+         'openmemex','width=520,height=420');}})();",
+        host().unwrap()
+    )
+}
+
+impl AddNote {
+    // Looks the note up among existing entries when it is a url, so the same
+    // page isn't captured, crawled and thumbnailed twice.
+    fn check_duplicate(&mut self) {
+        let canonical = canonical_url(&self.content);
+        if canonical == self.canonical {
+            return;
+        }
+        self.canonical = canonical.clone();
+        self.duplicate = None;
+        self.duplicate_task = None;
+        let canonical = match canonical {
+            Some(canonical) => canonical,
+            None => return,
+        };
+        let query = format!("http://{}/duplicates?url={}", host().unwrap(), encode(search_key(&canonical)));
+        let request = Request::get(&query)
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = self.link.callback_once(
+            move |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                AddNoteMsg::ReceiveDuplicates(canonical, data)
+            },
+        );
+        self.duplicate_task = FetchService::fetch(request, callback).ok();
+    }
+
+    fn clear(&mut self) {
+        self.content = String::from("");
+        self.tags = [].to_vec();
+        self.canonical = None;
+        self.duplicate = None;
+        self.duplicate_task = None;
+    }
+
+    fn view_duplicate(&self, entry: &Cache) -> Html {
+        let title = entry.content.clone().or_else(|| entry.url.clone()).unwrap_or_default();
+        html! {
+            <div class="duplicate-panel shadow-sm p-3 mb-5 bg-white rounded">
+                <div>{ format!("Already captured on {}", entry.date) }</div>
+                <h5>{ title }</h5>
+                <font color="grey">{ entry.url.clone().unwrap_or_default() }</font>
+                <p/>
+                <Link route=AppRoute::DetailById(entry.entry_id)>
+                    <button class="duplicate-button" onclick=self.link.callback(|_| AddNoteMsg::OpenDuplicate)>
+                        {"Open"}
+                    </button>
+                </Link>
+                <button class="duplicate-button" disabled=self.tags.is_empty()
+                    title="add the tags above to the existing entry"
+                    onclick=self.link.callback(|_| AddNoteMsg::TagDuplicate)>
+                    {"Add tags to it"}
+                </button>
+                <button class="duplicate-button" onclick=self.link.callback(|_| AddNoteMsg::CaptureAnyway)>
+                    {"Capture again"}
+                </button>
+            </div>
+        }
+    }
 }
 
 impl Component for AddNote {
     type Message = AddNoteMsg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self {
-            content: "".to_string(),
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let prefill = props.prefill.clone().unwrap_or(AddNotePayload {
+            note_content: String::from(""),
+            tags: vec![],
+        });
+        let mut add_note = Self {
+            content: prefill.note_content,
             tag: String::from(""),
-            tags: [].to_vec(),
+            tags: prefill.tags,
             link: link,
-            submit_task: None,
-        }
+            sync: SyncAgent::dispatcher(),
+            props,
+            canonical: None,
+            duplicate: None,
+            duplicate_task: None,
+            status: None,
+        };
+        // a shared link should be checked before the user hits Add Item
+        add_note.check_duplicate();
+        add_note
     }
-    fn change(&mut self, _props: Self::Properties) -> bool {
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props.open_entry_callback = props.open_entry_callback;
         false
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
-        let server = host().unwrap();
         match msg {
             AddNoteMsg::NoteEdit(content) => {
                 log::info!("note edit {:?}", content);
                 self.content = content;
-                false
+                self.status = None;
+                let was_duplicate = self.duplicate.is_some();
+                self.check_duplicate();
+                was_duplicate && self.duplicate.is_none()
             }
             AddNoteMsg::NoteKeyDown(keypress) => {
                 log::info!("note keydown {:?}", keypress.key());
@@ -91,38 +212,62 @@ impl Component for AddNote {
 
             AddNoteMsg::SubmitNote => {
                 log::info!("self.content {:?}", self.content);
-                let query = format!("http://{}/submit/note", server);
                 let payload = AddNotePayload {
                     note_content: self.content.clone(),
                     tags: self.tags.clone(),
                 };
-                let body = json!({"pnContent": self.content.clone(),
-                                       "pnTags": self.tags.clone()});
-                let request = Request::post(query)
-                    .header("Content-Type", "application/json")
-                    .body(Json(&body))
-                    .expect("Could not build request.");
-                log::info!("request is {:?}", request);
-
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<NoteResponse>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        AddNoteMsg::SubmitResponse(data)
-                    },
-                );
-                self.content = String::from(""); // TODO - oninput callback still fires and we're left with a black note
-                self.tags = [].to_vec();
-                log::info!("request payload {:?}", payload);
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.submit_task = Some(task);
+                log::info!("queueing note {:?}", payload);
+                // goes through the outbox so notes taken offline are kept
+                self.sync.send(SyncRequest::Enqueue(OutboxOp::Note(payload)));
+                self.clear(); // TODO - oninput callback still fires and we're left with a black note
                 true
             }
 
-            AddNoteMsg::SubmitResponse(data) => {
-                log::info!("submitted, response code received {:?}", data);
+            AddNoteMsg::ReceiveDuplicates(canonical, response) => {
+                // a later edit may have changed the url while this was in flight
+                if self.canonical.as_ref() != Some(&canonical) {
+                    return false;
+                }
+                self.duplicate_task = None;
+                match response {
+                    Ok(result) => {
+                        self.duplicate = result.into_iter().find(|entry| {
+                            entry.url.as_deref().and_then(canonical_url).as_ref() == Some(&canonical)
+                        });
+                    }
+                    Err(error) => log::info!("duplicate check error: {}", error),
+                }
+                true
+            }
+
+            AddNoteMsg::OpenDuplicate => {
+                if let Some(entry) = self.duplicate.clone() {
+                    self.props.open_entry_callback.emit(entry);
+                }
+                false
+            }
+
+            AddNoteMsg::TagDuplicate => {
+                let entry = match &self.duplicate {
+                    Some(entry) => entry,
+                    None => return false,
+                };
+                let payload = TagsPayload {
+                    entry_id: entry.entry_id,
+                    tags: self.tags.clone(),
+                };
+                log::info!("queueing tags {:?}", payload);
+                self.sync.send(SyncRequest::Enqueue(OutboxOp::Tags(payload)));
+                self.clear();
+                self.status = Some(String::from("Tags added to the existing entry"));
                 true
             }
 
+            AddNoteMsg::CaptureAnyway => {
+                self.link.send_message(AddNoteMsg::SubmitNote);
+                false
+            }
+
             AddNoteMsg::AddTag(tag_name) => {
                 log::info!("adding tag {:?}", tag_name);
                 self.tags.push(tag_name);
@@ -159,11 +304,27 @@ impl Component for AddNote {
                             } else {
                                 vec![]
                             }) }
-                    onsubmit={ self.link.callback(move |e: FocusEvent| AddNoteMsg::SubmitNote) }>
+                    onsubmit={ self.link.callback(move |_: FocusEvent| AddNoteMsg::SubmitNote) }>
                 </textarea>
                 <p/>
-                <input type="submit" value="Add Item" class="add-note-submit shadow-sm p-3 mb-5 bg-white rounded" 
-                    onclick = { self.link.callback(move |e: MouseEvent| AddNoteMsg::SubmitNote) } />
+                {
+                    match &self.duplicate {
+                        Some(entry) => self.view_duplicate(entry),
+                        None => html! {
+                            <input type="submit" value="Add Item" class="add-note-submit shadow-sm p-3 mb-5 bg-white rounded" 
+                                onclick = { self.link.callback(move |_: MouseEvent| AddNoteMsg::SubmitNote) } />
+                        },
+                    }
+                }
+                {
+                    match &self.status {
+                        Some(status) => html! { <font color="grey">{ status }</font> },
+                        None => html! {},
+                    }
+                }
+                <p/>
+                <font color="grey">{"Drag to your bookmarks bar to capture any page: "}</font>
+                <a class="bookmarklet" href=bookmarklet()>{"+ OpenMemex"}</a>
             </div>
         }
     }
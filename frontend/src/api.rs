@@ -11,19 +11,19 @@ pub struct Entry {
 
 // gallery view
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Cache {
-    #[serde(rename(deserialize = "cvTime"))]
+    #[serde(rename(serialize = "cvTime", deserialize = "cvTime"))]
     pub time: String,
-    #[serde(rename(deserialize = "cvForeignID"))]
+    #[serde(rename(serialize = "cvForeignID", deserialize = "cvForeignID"))]
     pub entry_id: i32,
-    #[serde(rename(deserialize = "cvContent"))]
+    #[serde(rename(serialize = "cvContent", deserialize = "cvContent"))]
     pub content: Option<String>,
-    #[serde(rename(deserialize = "cvDate"))]
+    #[serde(rename(serialize = "cvDate", deserialize = "cvDate"))]
     pub date: String,
-    #[serde(rename(deserialize = "cvUrl"))]
+    #[serde(rename(serialize = "cvUrl", deserialize = "cvUrl"))]
     pub url: Option<String>,
-    #[serde(rename(deserialize = "cvThumbnailFile"))]
+    #[serde(rename(serialize = "cvThumbnailFile", deserialize = "cvThumbnailFile"))]
     pub thumbnail_file: Option<String>,
 }
 
@@ -32,14 +32,34 @@ pub struct Tag {
     pub tag_name: String,
 }
 
+// one tag on one entry, from /link/entry/tags
 #[derive(Deserialize, Debug, Clone)]
+pub struct EntryTag {
+    #[serde(rename(deserialize = "etEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(deserialize = "etTag"))]
+    pub tag: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagCount {
+    #[serde(rename(serialize = "tcTag", deserialize = "tcTag"))]
+    pub tag: String,
+    #[serde(rename(serialize = "tcCount", deserialize = "tcCount"))]
+    pub count: usize,
+    // "date time" of the newest entry with the tag
+    #[serde(rename(serialize = "tcLatest", deserialize = "tcLatest"))]
+    pub latest: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Timestamp {
-    #[serde(rename(deserialize = "dtDay"))]
+    #[serde(rename(serialize = "dtDay", deserialize = "dtDay"))]
     pub day: (i32, i32, i32),
-    #[serde(rename(deserialize = "dtTimeOfDay"))]
+    #[serde(rename(serialize = "dtTimeOfDay", deserialize = "dtTimeOfDay"))]
     pub time_of_day: (i32, i32, i32),
-    #[serde(rename(deserialize = "dtUTC"))]
-    pub utc: i64
+    #[serde(rename(serialize = "dtUTC", deserialize = "dtUTC"))]
+    pub utc: i64,
 }
 
 // add_note
@@ -52,6 +72,30 @@ pub struct AddNotePayload {
     pub tags: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagsPayload {
+    #[serde(rename(serialize = "ptEntryID", deserialize = "ptEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(serialize = "ptTags", deserialize = "ptTags"))]
+    pub tags: Vec<String>,
+}
+
+// new text for an existing entry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EditPayload {
+    #[serde(rename(serialize = "peEntryID", deserialize = "peEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(serialize = "peContent", deserialize = "peContent"))]
+    pub content: String,
+}
+
+// fetch the page of an entry again, e.g. for a missing thumbnail
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecrawlPayload {
+    #[serde(rename(serialize = "prEntryID", deserialize = "prEntryID"))]
+    pub entry_id: i32,
+}
+
 // detail
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -65,3 +109,58 @@ pub struct CompletedPayload {
 pub struct CompletedResponse {
     pub code: i64,
 }
+
+// annotations
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnnotationPayload {
+    #[serde(rename(serialize = "paEntryID", deserialize = "paEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(serialize = "paContent", deserialize = "paContent"))]
+    pub content: String,
+}
+
+// number of annotations on one entry, from /all/annotations/counts
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnnotationCount {
+    #[serde(rename(deserialize = "acEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(deserialize = "acCount"))]
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Annotation {
+    #[serde(rename(serialize = "anEntryID", deserialize = "anEntryID"))]
+    pub entry_id: i32,
+    #[serde(rename(serialize = "anDate", deserialize = "anDate"))]
+    pub date: String,
+    #[serde(rename(serialize = "anTime", deserialize = "anTime"))]
+    pub time: String,
+    #[serde(rename(serialize = "anContent", deserialize = "anContent"))]
+    pub content: String,
+}
+
+// search
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchResult {
+    #[serde(rename(deserialize = "srEntry"))]
+    pub entry: Cache,
+    // which field matched best: title, url, note, tag, page or ocr
+    #[serde(rename(deserialize = "srField"))]
+    pub field: String,
+    #[serde(rename(deserialize = "srSnippet"))]
+    pub snippet: String,
+}
+
+// related entries
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RelatedEntry {
+    #[serde(rename(deserialize = "reEntry"))]
+    pub entry: Cache,
+    // what the entries have in common: tags, site, time or text
+    #[serde(rename(deserialize = "reReasons"))]
+    pub reasons: Vec<String>,
+}
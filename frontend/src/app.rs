@@ -1,61 +1,200 @@
 use crate::add_note::*;
 use crate::api::*;
 use crate::app_router::*;
+use crate::archived::*;
+use crate::capture::*;
 use crate::cards::*;
+use crate::collections::*;
 use crate::detail::*;
+use crate::graph::*;
 use crate::space::*;
+use crate::tag_admin::*;
+use crate::query::*;
 use crate::queue::*;
+use crate::search_box::*;
+use crate::search_results::*;
 use crate::settings::*;
+use crate::store;
+use crate::sync::*;
 use crate::timeline::*;
 use crate::tags::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use yew::agent::Bridged;
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::storage::{Area, StorageService};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
 use yew::{
-    format::{Json, Nothing},
+    format::{Json, Nothing, Text},
     prelude::*,
     utils::host,
 };
-use urlencoding::encode;
+use yew_router::agent::{RouteAgentDispatcher, RouteRequest};
 use yew_router::prelude::*;
 use chrono::*;
 
 pub type Link = RouterAnchor<AppRoute>;
 
-#[derive(Debug)]
+// pause in typing before the search box query runs
+const SEARCH_DEBOUNCE_MS: u64 = 300;
+// how long a deleted entry can be brought back from the toast
+const UNDO_SECONDS: u64 = 10;
+const TAG_MIN_KEY: &str = "openmemex.tag_min";
+const DEFAULT_TAG_MIN: usize = 10;
+
+fn tag_query(server: &str, min: usize) -> String {
+    format!("http://{}/all/tags/counts?min={}", server, min)
+}
+
 pub struct App {
     cache_task: Option<FetchTask>,
     tag_task: Option<FetchTask>,
+    search_task: Option<FetchTask>,
+    debounce_task: Option<TimeoutTask>,
+    // bumped for every query sent, responses for older ones are dropped
+    search_seq: u32,
+    sync: Box<dyn Bridge<SyncAgent>>,
     entries: Option<Vec<Cache>>,
+    entries_fresh: bool,
     selected_entry: Option<Cache>,
-    tags: Option<Vec<String>>,
+    tags: Option<Vec<TagCount>>,
+    // fewest entries a tag needs to be in the sidebar
+    tag_min: usize,
     selected_tags: HashSet<String>,
     link: ComponentLink<Self>,
     error: Option<String>,
     default_query: String,
     query: String,
     search_query: String,
+    search_error: Option<QueryError>,
+    // search box, tag and timeline filters together
+    gallery_query: GalleryQuery,
+    // Some while the gallery shows search results instead of cards
+    search_results: Option<Vec<SearchResult>>,
+    tag_query: String,
+    pending_sync: usize,
+    sync_conflicts: Vec<i32>,
+    // writes the server refused, dropped from the outbox
+    sync_rejected: usize,
+    // archive and restore requests in flight, one per entry so a second
+    // action doesn't drop the first
+    entry_tasks: HashMap<i32, FetchTask>,
+    // last entry moved to the archive, offered for undo until the timeout
+    undo_entry: Option<Cache>,
+    undo_task: Option<TimeoutTask>,
 }
 
 #[derive(Debug)]
 pub enum AppMsg {
     GetEntries,
-    ReceiveEntries(Result<Vec<Cache>, anyhow::Error>),
-    ReceiveTags(Result<Vec<String>, anyhow::Error>),
+    // responses tagged with the search_seq they were sent under
+    ReceiveEntries(u32, Result<Vec<Cache>, anyhow::Error>),
+    ReceiveSearchResults(u32, Result<Vec<SearchResult>, anyhow::Error>),
+    ReceiveTags(Result<Vec<TagCount>, anyhow::Error>),
+    // local store reads, tagged with the query they answer
+    ReceiveCachedEntries(String, Option<Vec<Cache>>),
+    ReceiveCachedTags(Option<Vec<TagCount>>),
+    SyncStatus(SyncStatus),
     KeyDown,
     // callback events
     CardClick(Option<Cache>),
+    OpenEntry(Cache),
     TagClick(Option<String>),
+    CollectionClick(GalleryQuery),
     TimelineEvt(Option<(NaiveDateTime, NaiveDateTime)>),
 
     //
     SortByDate,
     SortByUrl,
+    TagMin(usize),
     SearchEdit(String),
-    SearchKeyDown(KeyboardEvent),
+    SearchDebounced,
     SearchSubmit,
+    EntryEdited(Cache),
+    DeleteEntry(Cache),
+    EntryArchived(Cache, bool),
+    UndoDelete,
+    EntryRestored(i32, bool),
+    DismissUndo,
+    Reload,
 }
 
 impl App {
+    // Sends the gallery query to the listing or, with free text, the ranked search
+    // Replacing or clearing the tasks drops whatever request was in flight.
+    fn run_query(&mut self) {
+        let server = host().unwrap();
+        self.query = self.gallery_query.to_url(&server);
+        self.search_error = None;
+        self.debounce_task = None;
+        self.search_seq = self.search_seq.wrapping_add(1);
+        log::info!("Query {} is: {:?}", self.search_seq, &self.query);
+        if !self.gallery_query.is_search() {
+            self.search_results = None;
+            self.search_task = None;
+            self.link.send_message(AppMsg::GetEntries);
+            return;
+        }
+        self.cache_task = None;
+        let seq = self.search_seq;
+        let request = Request::get(&self.query)
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = self.link.callback_once(
+            move |response: Response<Json<Result<Vec<SearchResult>, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                AppMsg::ReceiveSearchResults(seq, data)
+            },
+        );
+        self.search_task = FetchService::fetch(request, callback).ok();
+    }
+
+    // POSTs archive/entry/{id} or restore/entry/{id}, reporting whether it worked
+    fn post_entry_action<F>(&mut self, action: &str, entry_id: i32, to_msg: F)
+    where
+        F: FnOnce(bool) -> AppMsg + 'static,
+    {
+        let request = Request::post(format!("http://{}/{}/entry/{}", host().unwrap(), action, entry_id))
+            .header("Content-Type", "application/json")
+            .body(Json(&serde_json::json!(null)))
+            .expect("Could not build request.");
+        let callback = self
+            .link
+            .callback_once(move |response: Response<Text>| to_msg(response.status().is_success()));
+        if let Ok(task) = FetchService::fetch(request, callback) {
+            self.entry_tasks.insert(entry_id, task);
+        }
+    }
+
+    fn get_tags(&mut self) {
+        store::get_json(store::TAGS, &self.tag_query, self.link.callback(AppMsg::ReceiveCachedTags));
+        log::info!("submitting tag request");
+        let request = Request::get(&self.tag_query)
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = self.link.callback_once(
+            |response: Response<Json<Result<Vec<TagCount>, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                AppMsg::ReceiveTags(data)
+            },
+        );
+        let task = FetchService::fetch(request, callback).expect("failed to start request");
+        self.tag_task = Some(task);
+    }
+
+    // what the gallery currently lists, in order
+    fn result_entries(&self) -> Vec<Cache> {
+        match &self.search_results {
+            Some(results) => results.iter().map(|result| result.entry.clone()).collect(),
+            None => self.entries.clone().unwrap_or_default(),
+        }
+    }
+
+    // for query changes made outside the search box
+    fn run_query_from_sidebar(&mut self) {
+        self.search_query = self.gallery_query.to_string();
+        self.run_query();
+    }
+
     fn view_navbar(&self) -> Html {
         html! {
             <nav class="navbar navbar-expand-lg navbar-light bg-light">
@@ -82,6 +221,15 @@ impl App {
                         <li class="nav-item" accesskey="d">
                             <Link route=AppRoute::Detail><div class="nav-link">{ "Detail" }</div></Link>
                         </li>
+                        <li class="nav-item">
+                            <Link route=AppRoute::Graph><div class="nav-link">{ "Graph" }</div></Link>
+                        </li>
+                        <li class="nav-item">
+                            <Link route=AppRoute::TagAdmin><div class="nav-link">{ "Tags" }</div></Link>
+                        </li>
+                        <li class="nav-item">
+                            <Link route=AppRoute::Archived><div class="nav-link">{ "Archive" }</div></Link>
+                        </li>
                         /*
                         <li class="nav-item" accesskey="s">
                             <Link route=AppRoute::Space><div class="nav-link">{ "Space" }</div></Link>
@@ -95,9 +243,60 @@ impl App {
                         */
                     </ul>
                 </div>
+                { self.view_sync_status() }
             </nav>
         }
     }
+
+    fn view_undo(&self) -> Html {
+        match &self.undo_entry {
+            Some(entry) => html! {
+                <div class="undo-toast shadow bg-white rounded">
+                    { format!("Moved \"{}\" to the archive ", entry.content.clone().unwrap_or_default()) }
+                    <button class="batch-button" onclick=self.link.callback(|_| AppMsg::UndoDelete)>{"Undo"}</button>
+                    <span class="undo-dismiss" onclick=self.link.callback(|_| AppMsg::DismissUndo)>{"×"}</span>
+                </div>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_sync_status(&self) -> Html {
+        let conflicts = if self.sync_conflicts.is_empty() {
+            html! {}
+        } else {
+            html! {
+                <span class="sync-conflict" title="Edited on both sides, both versions were kept as annotations">
+                    { format!("conflict on {:?}", self.sync_conflicts) }
+                </span>
+            }
+        };
+        let pending = if self.pending_sync == 0 {
+            html! {}
+        } else {
+            html! {
+                <span class="sync-pending" title="Waiting for the server">
+                    { format!("{} unsynced", self.pending_sync) }
+                </span>
+            }
+        };
+        let rejected = if self.sync_rejected == 0 {
+            html! {}
+        } else {
+            html! {
+                <span class="sync-conflict" title="The server refused these changes, see the console log">
+                    { format!("{} not saved", self.sync_rejected) }
+                </span>
+            }
+        };
+        html! {
+            <div class="sync-status">
+                { pending }
+                { conflicts }
+                { rejected }
+            </div>
+        }
+    }
 }
 
 impl Component for App {
@@ -112,10 +311,23 @@ impl Component for App {
         log::info!("sent message");
         // let kb_cb = link.callback(Msg::KeyDown);
         let default_query = format!("http://{}/all/cache?limit=150", server).to_string();
+        let sync = SyncAgent::bridge(link.callback(AppMsg::SyncStatus));
+        let tag_min = StorageService::new(Area::Local)
+            .ok()
+            .and_then(|storage| {
+                let Json(min) = storage.restore(TAG_MIN_KEY);
+                min.ok()
+            })
+            .unwrap_or(DEFAULT_TAG_MIN);
         Self {
             cache_task: None,
             tag_task: None,
+            search_task: None,
+            debounce_task: None,
+            search_seq: 0,
+            sync,
             entries: None,
+            entries_fresh: false,
             tags: None,
             selected_entry: None,
             selected_tags: HashSet::new(),
@@ -124,6 +336,17 @@ impl Component for App {
             default_query: default_query.clone(),
             query: default_query.clone(),
             search_query: String::from(""),
+            search_error: None,
+            gallery_query: GalleryQuery::default(),
+            search_results: None,
+            tag_query: tag_query(&server, tag_min),
+            tag_min,
+            pending_sync: 0,
+            sync_conflicts: vec![],
+            sync_rejected: 0,
+            entry_tasks: HashMap::new(),
+            undo_entry: None,
+            undo_task: None,
         }
     }
 
@@ -136,42 +359,54 @@ impl Component for App {
         log::info!("host is {:?}", server);
         match msg {
             AppMsg::GetEntries => {
+                // serve whatever the local store has for this query while the server answers
+                self.entries_fresh = false;
+                let query = self.query.clone();
+                store::get_json(
+                    store::QUERIES,
+                    &self.query,
+                    self.link.callback(move |cached| AppMsg::ReceiveCachedEntries(query.clone(), cached)),
+                );
                 // define request
                 log::info!("submitting cache request: {:?}", self.query);
                 let request = Request::get(&self.query)
                     .body(Nothing)
                     .expect("Could not build request.");
                 // define callback
+                let seq = self.search_seq;
                 let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
+                    move |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
                         let Json(data) = response.into_body();
-                        AppMsg::ReceiveEntries(data)
+                        AppMsg::ReceiveEntries(seq, data)
                     },
                 );
                 // task
                 let task = FetchService::fetch(request, callback).expect("failed to start request");
                 self.cache_task = Some(task);
-                // define request
-                log::info!("submitting tag request");
-                let request = Request::get(format!("http://{}/all/tags?min=10", server))
-                    .body(Nothing)
-                    .expect("Could not build request.");
-                // define callback
-                let callback = self.link.callback_once(
-                    |response: Response<Json<Result<Vec<String>, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        AppMsg::ReceiveTags(data)
-                    },
-                );
-                // task
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.tag_task = Some(task);
+                self.get_tags();
                 true // redraw page
             }
-            AppMsg::ReceiveEntries(response) => {
+            AppMsg::TagMin(min) => {
+                self.tag_min = min;
+                if let Ok(mut storage) = StorageService::new(Area::Local) {
+                    storage.store(TAG_MIN_KEY, Json(&min));
+                }
+                self.tag_query = tag_query(&server, min);
+                self.get_tags();
+                true
+            }
+            AppMsg::ReceiveEntries(seq, response) => {
+                if seq != self.search_seq {
+                    log::info!("dropping stale entries for query {}", seq);
+                    return false;
+                }
                 match response {
                     Ok(result) => {
+                        store::put_json(store::QUERIES, &self.query, &result);
                         self.entries = Some(result);
+                        self.entries_fresh = true;
+                        // the server is reachable again, push anything waiting
+                        self.sync.send(SyncRequest::Flush);
                     }
                     Err(error) => {
                         log::info!("cache receive error:");
@@ -182,9 +417,28 @@ impl Component for App {
                 self.cache_task = None;
                 true
             }
+            AppMsg::ReceiveSearchResults(seq, response) => {
+                if seq != self.search_seq {
+                    log::info!("dropping stale search results for query {}", seq);
+                    return false;
+                }
+                match response {
+                    Ok(result) => {
+                        log::info!("{} search results", result.len());
+                        self.search_results = Some(result);
+                    }
+                    Err(error) => {
+                        log::info!("search receive error: {}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+                self.search_task = None;
+                true
+            }
             AppMsg::ReceiveTags(response) => {
                 match response {
                     Ok(result) => {
+                        store::put_json(store::TAGS, &self.tag_query, &result);
                         self.tags = Some(result);
                     }
                     Err(error) => {
@@ -196,6 +450,39 @@ impl Component for App {
                 self.tag_task = None;
                 true
             }
+            AppMsg::ReceiveCachedEntries(query, cached) => {
+                if self.entries_fresh || query != self.query {
+                    return false;
+                }
+                match cached {
+                    Some(result) => {
+                        log::info!("{} entries from local store", result.len());
+                        self.entries = Some(result);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            AppMsg::ReceiveCachedTags(cached) => {
+                if self.tags.is_some() || cached.is_none() {
+                    return false;
+                }
+                self.tags = cached;
+                true
+            }
+            AppMsg::SyncStatus(status) => {
+                log::info!("sync status {:?}", status);
+                match status {
+                    SyncStatus::Pending(count) => self.pending_sync = count,
+                    SyncStatus::Conflict(entry_id) => self.sync_conflicts.push(entry_id),
+                    SyncStatus::Rejected(item, status) => {
+                        log::info!("server refused {:?} with {}", item.op, status);
+                        self.sync_rejected += 1;
+                    }
+                    SyncStatus::Queued(_) | SyncStatus::Sent(_) => return false,
+                }
+                true
+            }
             AppMsg::KeyDown => {
                 log::info!("keydown event");
                 false
@@ -205,33 +492,39 @@ impl Component for App {
                 log::info!("selected entry is {:?}", self.selected_entry);
                 true
             }
+            AppMsg::OpenEntry(entry) => {
+                log::info!("opening existing entry {}", entry.entry_id);
+                self.selected_entry = Some(entry);
+                true
+            }
             AppMsg::TagClick(tag) => {
                 log::info!("tag click event");
                 log::info!("{:?}", tag);
-                self.query = match tag {
-                    Some(tag_name) => {
-                        format!("http://{}/all/cache?sort=time&tag={}&limit=150", server, tag_name)
-                    }
-                    None => format!("http://{}/all/cache?sort=time&limit=150", server),
-                };
-                log::info!("Query is: {:?}", &self.query);
-                // self.query = query.clone(); // TODO - make queryparams compose
-                self.link.send_message(AppMsg::GetEntries);
-                false
+                self.gallery_query.tags = tag.into_iter().collect();
+                self.run_query_from_sidebar();
+                true
+            }
+            AppMsg::CollectionClick(query) => {
+                log::info!("collection click {}", query);
+                self.gallery_query = query;
+                self.run_query_from_sidebar();
+                true
             }
             AppMsg::TimelineEvt(evt) => {
                 log::info!("Timeline event");
-                self.query = match evt {
+                match evt {
                     Some((dt_min, dt_max)) => {
-                        format!("http://{}/all/cache?sort=time&startDate={}&endDate={}&limit=150", server,
-                            dt_min.format("%Y-%m-%d").to_string(), 
-                            dt_max.format("%Y-%m-%d").to_string())
+                        self.gallery_query.after = Some(dt_min.date());
+                        // before: is exclusive, the timeline range isn't
+                        self.gallery_query.before = Some(dt_max.date() + Duration::days(1));
                     }
-                    None => format!("http://{}/all/cache?sort=time&limit=150", server),
-                };
-                log::info!("Query is: {:?}", &self.query);
-                self.link.send_message(AppMsg::GetEntries);
-                false
+                    None => {
+                        self.gallery_query.after = None;
+                        self.gallery_query.before = None;
+                    }
+                }
+                self.run_query_from_sidebar();
+                true
             }
             AppMsg::SortByDate => {
                 log::info!("sort date");
@@ -245,52 +538,172 @@ impl Component for App {
                 self.link.send_message(AppMsg::GetEntries);
                 true
             }
-            AppMsg::SearchKeyDown(keypress) => {
-                log::info!("search keydown {:?}", keypress.key());
-                if keypress.key() == "Enter" {
-                    self.link.send_message(AppMsg::SearchSubmit);
-                }
-                false
-            }
             AppMsg::SearchEdit(query) => {
                 self.search_query = query;
-                false
+                let had_error = self.search_error.is_some();
+                match parse(&self.search_query) {
+                    Ok(query) => {
+                        self.search_error = None;
+                        // runs once typing pauses, a new keystroke restarts the wait
+                        self.debounce_task = if query != self.gallery_query {
+                            Some(TimeoutService::spawn(
+                                std::time::Duration::from_millis(SEARCH_DEBOUNCE_MS),
+                                self.link.callback(|_| AppMsg::SearchDebounced),
+                            ))
+                        } else {
+                            None
+                        };
+                    }
+                    Err(error) => {
+                        // half typed operators shouldn't replace the results
+                        self.search_error = Some(error);
+                        self.debounce_task = None;
+                    }
+                }
+                had_error || self.search_error.is_some()
+            }
+            AppMsg::SearchDebounced => {
+                self.debounce_task = None;
+                match parse(&self.search_query) {
+                    Ok(query) if query != self.gallery_query => {
+                        self.gallery_query = query;
+                        self.run_query();
+                        true
+                    }
+                    _ => false,
+                }
             }
             AppMsg::SearchSubmit => {
-                self.query = format!("http://{}/search/{}", server, encode(self.search_query.trim()).to_string());
-                log::info!("Query: {}", &self.query);
-                self.link.send_message(AppMsg::GetEntries);
+                match parse(&self.search_query) {
+                    Ok(query) => {
+                        self.gallery_query = query;
+                        self.run_query();
+                    }
+                    Err(error) => self.search_error = Some(error),
+                }
+                true
+            }
+            AppMsg::EntryEdited(entry) => {
+                // update the entry in place rather than reloading the gallery
+                let entry_id = entry.entry_id;
+                if let Some(e) = self.entries.iter_mut().flatten().find(|e| e.entry_id == entry_id) {
+                    *e = entry.clone();
+                }
+                if let Some(r) = self.search_results.iter_mut().flatten().find(|r| r.entry.entry_id == entry_id) {
+                    r.entry = entry.clone();
+                }
+                self.selected_entry = Some(entry);
+                // a tag change can move the entry in or out of a tag filter
+                if !self.gallery_query.tags.is_empty() || !self.gallery_query.exclude_tags.is_empty() {
+                    self.run_query();
+                }
+                true
+            }
+            AppMsg::DeleteEntry(entry) => {
+                let entry_id = entry.entry_id;
+                self.post_entry_action("archive", entry_id, move |success| AppMsg::EntryArchived(entry, success));
                 false
             }
+            AppMsg::EntryArchived(entry, success) => {
+                self.entry_tasks.remove(&entry.entry_id);
+                if !success {
+                    self.error = Some(format!("Could not delete entry {}", entry.entry_id));
+                    return false;
+                }
+                let entry_id = entry.entry_id;
+                if let Some(entries) = self.entries.as_mut() {
+                    entries.retain(|e| e.entry_id != entry_id);
+                }
+                if let Some(results) = self.search_results.as_mut() {
+                    results.retain(|r| r.entry.entry_id != entry_id);
+                }
+                let viewed = self.selected_entry.as_ref().map(|e| e.entry_id) == Some(entry_id);
+                if viewed {
+                    self.selected_entry = None;
+                }
+                // Detail would keep showing the entry it had, go back to the gallery
+                let route = AppRoute::switch(RouteService::<()>::new().get_route());
+                if matches!(route, Some(AppRoute::DetailById(id)) if id == entry_id)
+                    || (viewed && matches!(route, Some(AppRoute::Detail)))
+                {
+                    RouteAgentDispatcher::<()>::new().send(RouteRequest::ChangeRoute(Route::from(AppRoute::Gallery)));
+                }
+                self.undo_entry = Some(entry);
+                self.undo_task = Some(TimeoutService::spawn(
+                    std::time::Duration::from_secs(UNDO_SECONDS),
+                    self.link.callback(|_| AppMsg::DismissUndo),
+                ));
+                true
+            }
+            AppMsg::UndoDelete => {
+                self.undo_task = None;
+                match self.undo_entry.take() {
+                    Some(entry) => {
+                        let entry_id = entry.entry_id;
+                        self.post_entry_action("restore", entry_id, move |success| AppMsg::EntryRestored(entry_id, success));
+                        true
+                    }
+                    None => false,
+                }
+            }
+            AppMsg::EntryRestored(entry_id, success) => {
+                self.entry_tasks.remove(&entry_id);
+                if success {
+                    self.run_query();
+                } else {
+                    self.error = Some(String::from("Could not restore the entry, it is in the archive"));
+                }
+                true
+            }
+            AppMsg::DismissUndo => {
+                self.undo_entry = None;
+                self.undo_task = None;
+                true
+            }
+            AppMsg::Reload => {
+                self.run_query();
+                true
+            }
         }
     }
 
     fn view(&self) -> Html {
-        let empty_vec = &[].to_vec();
-        let exist_tags = self.tags.as_ref().unwrap_or(empty_vec);
         let card_callback = self.link.callback(move |card| AppMsg::CardClick(card));
         let tag_callback = self.link.callback(move |tag| AppMsg::TagClick(tag));
         let timeline_callback = self.link.callback(move |dt| AppMsg::TimelineEvt(dt));
 
-        let button_class = "sort-button shadow-sm p-3 mb-5 bg-white rounded";
-
+        let selected_id = self.selected_entry.as_ref().map(|e| e.entry_id);
         let gallery = html! {
             <div>
-                /*
-                    <button class=button_class onclick=self.link.callback(|m| { 
-                        AppMsg::SortByDate
-                        })> {"▼ Date"}</button>
-                */
-                <input type="text" class="search-input shadow-sm p-3 mb-5 bg-white rounded" placeholder="Search" accesskey="/" 
-                oninput = { self.link.callback(move |e: InputData| AppMsg::SearchEdit(e.value)) }
-                onkeydown = { self.link.callback(move |e: KeyboardEvent| AppMsg::SearchKeyDown(e)) }
-                />
+                <SearchBox value=self.search_query.clone()
+                    edit_callback=self.link.callback(AppMsg::SearchEdit)
+                    submit_callback=self.link.batch_callback(|query| vec![AppMsg::SearchEdit(query), AppMsg::SearchSubmit])/>
+                {
+                    match &self.search_error {
+                        Some(error) => html! { <div class="search-error">{ error.to_string() }</div> },
+                        None => html! {},
+                    }
+                }
                 <Timeline timeline_callback = timeline_callback/>
                 <p/>
                 <div class="twocol">
-                    <Cards entries=self.entries.clone() card_click_callback=card_callback/>
+                {
+                    match &self.search_results {
+                        Some(results) => html! {
+                            <SearchResults results=results.clone() terms=self.gallery_query.terms.clone()
+                                card_click_callback=card_callback selected_id=selected_id/>
+                        },
+                        None => html! {
+                            <Cards entries=self.entries.clone() card_click_callback=card_callback selected_id=selected_id
+                                batch_done_callback=self.link.callback(|_| AppMsg::Reload)/>
+                        },
+                    }
+                }
                     <div>
-                        <Tags tags=exist_tags.clone() tag_click_callback=tag_callback/>
+                        <Tags tags=self.tags.clone() tag_click_callback=tag_callback
+                            min=self.tag_min min_change_callback=self.link.callback(AppMsg::TagMin)/>
+                        <Collections current=self.gallery_query.clone()
+                            collection_click_callback=self.link.callback(AppMsg::CollectionClick)/>
                         //<p/>
                         //<input type="checkbox" id="hidecompleted" name="hidecompleted"/>
                         //<label style="height:10%; margin-left: 10px"> {"Hide Completed"} </label>
@@ -300,21 +713,46 @@ impl Component for App {
         };
 
         let entry = self.selected_entry.clone();
+        let result_entries = self.result_entries();
+        let open_entry_callback = self.link.callback(AppMsg::OpenEntry);
+        let delete_callback = self.link.callback(AppMsg::DeleteEntry);
+        let edit_callback = self.link.callback(AppMsg::EntryEdited);
+        let reload_callback = self.link.callback(|_| AppMsg::Reload);
+        let tags_changed_callback = self.link.callback(|_| AppMsg::Reload);
+        let graph_tag_callback = self.link.callback(|tag| AppMsg::TagClick(Some(tag)));
 
         log::info!("switch with entry as {:?}", &entry);
         let render = Router::render(move |switch: AppRoute| match switch {
             AppRoute::Gallery => gallery.clone(),
-            AppRoute::AddNote => html! { <AddNote/> },
-            AppRoute::Detail => html! { <Detail entry=entry.clone() /> },
+            AppRoute::AddNote => html! { <AddNote prefill=share_target_payload() open_entry_callback=open_entry_callback.clone() /> },
+            AppRoute::Capture => html! { <Capture/> },
+            AppRoute::Detail => html! {
+                <Detail entry=entry.clone() delete_callback=delete_callback.clone() edit_callback=edit_callback.clone()
+                    open_entry_callback=open_entry_callback.clone() entries=result_entries.clone() />
+            },
+            AppRoute::DetailById(entry_id) => html! {
+                <Detail entry=entry.clone() entry_id=Some(entry_id) delete_callback=delete_callback.clone()
+                    edit_callback=edit_callback.clone() open_entry_callback=open_entry_callback.clone()
+                    entries=result_entries.clone() />
+            },
             AppRoute::Space => html! { <Space /> },
             AppRoute::Queue => html! { <Queue /> },
             AppRoute::Settings => html! { <Settings/> },
+            AppRoute::Archived => html! { <Archived restore_callback=reload_callback.clone() /> },
+            AppRoute::Graph => html! { <TagGraph tag_click_callback=graph_tag_callback.clone() /> },
+            AppRoute::TagAdmin => html! { <TagAdmin tags_changed_callback=tags_changed_callback.clone() /> },
         });
 
+        // the bookmarklet popup only needs the capture form
+        if let Some(AppRoute::Capture) = AppRoute::switch(RouteService::<()>::new().get_route()) {
+            return html! { <Router<AppRoute, ()> render=render/> };
+        }
+
         html! {
             <div class="main-outer" onkeydown={ self.link.callback(move |e: KeyboardEvent|
                 { e.stop_propagation(); AppMsg::KeyDown })}>
                 { self.view_navbar() }
+                { self.view_undo() }
                 <div class="main-inner">
                     <div class="main-top">
                     /*
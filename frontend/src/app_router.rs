@@ -1,3 +1,5 @@
+use url::Url;
+use yew::utils::window;
 use yew_router::prelude::*;
 
 #[derive(Switch, Debug, Clone)]
@@ -10,6 +12,11 @@ pub enum AppRoute {
     */
     #[to = "/frontend/addnote"]
     AddNote,
+    #[to = "/frontend/capture"]
+    Capture,
+    // before Detail, which would match the id as well
+    #[to = "/frontend/detail/{id}"]
+    DetailById(i32),
     #[to = "/frontend/detail"]
     Detail,
     #[to = "/frontend/space"]
@@ -20,4 +27,22 @@ pub enum AppRoute {
     Gallery,
     #[to = "/frontend/settings"]
     Settings,
+    #[to = "/frontend/archived"]
+    Archived,
+    #[to = "/frontend/tags"]
+    TagAdmin,
+    #[to = "/frontend/graph"]
+    Graph,
+}
+
+// Non-empty query parameter of the current page url, for routes opened from
+// outside the app (share targets, bookmarklets).
+pub fn query_param(name: &str) -> Option<String> {
+    let href = window().location().href().ok()?;
+    let parsed = Url::parse(&href).ok()?;
+    let value = parsed
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.trim().to_string());
+    value.filter(|value| !value.is_empty())
 }
@@ -0,0 +1,187 @@
+use crate::api::*;
+use serde_json::json;
+use yew::format::{Json, Nothing, Text};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::DialogService;
+use yew::utils::host;
+
+// Entries deleted from the gallery, restored from here or purged for good.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveAction {
+    Restore,
+    // removes the entry with its cached page, screenshot and thumbnail
+    Purge,
+}
+
+impl ArchiveAction {
+    fn path(&self) -> &str {
+        match self {
+            ArchiveAction::Restore => "restore",
+            ArchiveAction::Purge => "purge",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ArchivedMsg {
+    GetArchived,
+    ReceiveArchived(Result<Vec<Cache>, anyhow::Error>),
+    Run(ArchiveAction, i32),
+    Done(ArchiveAction, i32, bool),
+}
+
+pub struct Archived {
+    link: ComponentLink<Self>,
+    props: Props,
+    entries: Option<Vec<Cache>>,
+    fetch_task: Option<FetchTask>,
+    action_tasks: Vec<(i32, FetchTask)>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    // an entry came back to the gallery
+    #[prop_or_default]
+    pub restore_callback: Callback<()>,
+}
+
+impl Archived {
+    fn view_entry(&self, entry: &Cache) -> Html {
+        let entry_id = entry.entry_id;
+        let busy = self.action_tasks.iter().any(|(id, _)| *id == entry_id);
+        let title = entry.content.clone().or_else(|| entry.url.clone()).unwrap_or_default();
+        html! {
+            <div class="archived-entry shadow-sm p-3 mb-3 bg-white rounded">
+                <img class="archived-thumbnail" src=entry.thumbnail_file.clone().unwrap_or_default()/>
+                <div class="archived-text">
+                    <div>{ &entry.date }</div>
+                    {
+                        match entry.url.as_ref() {
+                            Some(url) => html! { <a href=url.to_string()>{ title }</a> },
+                            None => html! { { title } },
+                        }
+                    }
+                </div>
+                <button class="batch-button" disabled=busy
+                    onclick=self.link.callback(move |_| ArchivedMsg::Run(ArchiveAction::Restore, entry_id))>
+                    {"Restore"}
+                </button>
+                <button class="batch-button batch-delete" disabled=busy
+                    onclick=self.link.callback(move |_| ArchivedMsg::Run(ArchiveAction::Purge, entry_id))>
+                    {"Delete forever"}
+                </button>
+            </div>
+        }
+    }
+}
+
+impl Component for Archived {
+    type Message = ArchivedMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        link.send_message(ArchivedMsg::GetArchived);
+        Self {
+            link,
+            props,
+            entries: None,
+            fetch_task: None,
+            action_tasks: vec![],
+            error: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            ArchivedMsg::GetArchived => {
+                let request = Request::get(&format!("http://{}/all/archived", host().unwrap()))
+                    .body(Nothing)
+                    .expect("Could not build request.");
+                let callback = self.link.callback_once(
+                    |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
+                        let Json(data) = response.into_body();
+                        ArchivedMsg::ReceiveArchived(data)
+                    },
+                );
+                self.fetch_task = FetchService::fetch(request, callback).ok();
+                false
+            }
+            ArchivedMsg::ReceiveArchived(response) => {
+                match response {
+                    Ok(entries) => self.entries = Some(entries),
+                    Err(error) => {
+                        log::info!("archived receive error: {}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+                self.fetch_task = None;
+                true
+            }
+            ArchivedMsg::Run(action, entry_id) => {
+                if action == ArchiveAction::Purge
+                    && !DialogService::confirm("Delete this entry and its cached page, screenshot and thumbnail for good?")
+                {
+                    return false;
+                }
+                let request = Request::post(format!("http://{}/{}/entry/{}", host().unwrap(), action.path(), entry_id))
+                    .header("Content-Type", "application/json")
+                    .body(Json(&json!(null)))
+                    .expect("Could not build request.");
+                let callback = self.link.callback_once(move |response: Response<Text>| {
+                    ArchivedMsg::Done(action, entry_id, response.status().is_success())
+                });
+                match FetchService::fetch(request, callback) {
+                    Ok(task) => self.action_tasks.push((entry_id, task)),
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            ArchivedMsg::Done(action, entry_id, success) => {
+                self.action_tasks.retain(|(id, _)| *id != entry_id);
+                if !success {
+                    self.error = Some(format!("Could not {} entry {}", action.path(), entry_id));
+                    return true;
+                }
+                if let Some(entries) = self.entries.as_mut() {
+                    entries.retain(|entry| entry.entry_id != entry_id);
+                }
+                if action == ArchiveAction::Restore {
+                    self.props.restore_callback.emit(());
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let error = match &self.error {
+            Some(error) => html! { <div class="search-error">{ error }</div> },
+            None => html! {},
+        };
+        let entries = match &self.entries {
+            None => html! { <div>{"Loading..."}</div> },
+            Some(entries) if entries.is_empty() => html! { <div>{"The archive is empty"}</div> },
+            Some(entries) => html! {
+                <>
+                    <font color="grey">{ format!("{} archived", entries.len()) }</font>
+                    { for entries.iter().map(|entry| self.view_entry(entry)) }
+                </>
+            },
+        };
+        html! {
+            <div class="archived">
+                <h4>{"Archive"}</h4>
+                { error }
+                { entries }
+            </div>
+        }
+    }
+}
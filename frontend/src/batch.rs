@@ -0,0 +1,334 @@
+use crate::api::*;
+use serde_json::json;
+use std::collections::VecDeque;
+use urlencoding::encode;
+use yew::format::{Json, Text};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::utils::host;
+
+// requests in flight at once while a batch runs
+const BATCH_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchAction {
+    AddTag(String),
+    RemoveTag(String),
+    Complete(bool),
+    Recrawl,
+    // soft delete, the entries go to the archive
+    Delete,
+    Restore,
+}
+
+impl BatchAction {
+    fn label(&self) -> String {
+        match self {
+            BatchAction::AddTag(tag) => format!("Tagging with {}", tag),
+            BatchAction::RemoveTag(tag) => format!("Removing tag {}", tag),
+            BatchAction::Complete(true) => String::from("Marking completed"),
+            BatchAction::Complete(false) => String::from("Marking uncompleted"),
+            BatchAction::Recrawl => String::from("Re-crawling"),
+            BatchAction::Delete => String::from("Moving to archive"),
+            BatchAction::Restore => String::from("Restoring"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchMsg {
+    TagEdit(String),
+    Run(BatchAction),
+    Sent(i32, bool),
+    Undo,
+    Retry,
+    Clear,
+}
+
+// Action bar for the cards selected in the gallery
+pub struct BatchBar {
+    link: ComponentLink<Self>,
+    props: Props,
+    tag: String,
+    running: Option<BatchAction>,
+    // every entry of the running batch
+    batch: Vec<i32>,
+    queue: VecDeque<i32>,
+    tasks: Vec<(i32, FetchTask)>,
+    total: usize,
+    done: usize,
+    failed: Vec<i32>,
+    // outcome of the last finished batch
+    report: Option<String>,
+    // entries the last delete archived, what Undo restores
+    undo: Vec<i32>,
+    // action of the last batch while some of it failed, what Retry runs again
+    retry: Option<BatchAction>,
+    // built when the selection changes, not on every render
+    export_href: String,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub entries: Vec<Cache>,
+    // a batch finished and the gallery should reload
+    pub done_callback: Callback<()>,
+    pub clear_callback: Callback<()>,
+}
+
+fn export_href(entries: &[Cache]) -> String {
+    let json = serde_json::to_string_pretty(entries).unwrap_or_default();
+    format!("data:application/json;charset=utf-8,{}", encode(&json))
+}
+
+impl BatchBar {
+    fn send(&mut self, entry_id: i32) -> Result<FetchTask, anyhow::Error> {
+        let server = host().unwrap();
+        let callback = self
+            .link
+            .callback_once(move |response: Response<Text>| BatchMsg::Sent(entry_id, response.status().is_success()));
+        let (path, body) = match self.running.as_ref() {
+            Some(BatchAction::AddTag(tag)) => (
+                String::from("submit/tags"),
+                json!({"ptEntryID": entry_id, "ptTags": [tag]}),
+            ),
+            Some(BatchAction::RemoveTag(tag)) => (
+                String::from("submit/untag"),
+                json!({"ptEntryID": entry_id, "ptTags": [tag]}),
+            ),
+            Some(BatchAction::Complete(state)) => (
+                String::from("submit/completed"),
+                json!({"pcEntryID": entry_id, "pcState": state}),
+            ),
+            Some(BatchAction::Recrawl) => (String::from("submit/recrawl"), json!({"prEntryID": entry_id})),
+            // the id is in the path, there is no body
+            Some(BatchAction::Restore) => (format!("restore/entry/{}", entry_id), json!(null)),
+            Some(BatchAction::Delete) | None => (format!("archive/entry/{}", entry_id), json!(null)),
+        };
+        let request = Request::post(format!("http://{}/{}", server, path))
+            .header("Content-Type", "application/json")
+            .body(Json(&body))
+            .expect("Could not build request.");
+        FetchService::fetch(request, callback)
+    }
+
+    fn start(&mut self, action: BatchAction, entry_ids: Vec<i32>) {
+        self.queue = entry_ids.iter().copied().collect();
+        self.batch = entry_ids;
+        self.total = self.queue.len();
+        self.done = 0;
+        self.failed = vec![];
+        self.report = None;
+        self.undo = vec![];
+        self.retry = None;
+        self.running = Some(action);
+        self.pump();
+    }
+
+    // keeps up to BATCH_CONCURRENCY requests going, reports once all are back
+    fn pump(&mut self) {
+        while self.tasks.len() < BATCH_CONCURRENCY {
+            let entry_id = match self.queue.pop_front() {
+                Some(entry_id) => entry_id,
+                None => break,
+            };
+            match self.send(entry_id) {
+                Ok(task) => self.tasks.push((entry_id, task)),
+                Err(error) => {
+                    log::info!("batch request for {} failed to start: {}", entry_id, error);
+                    self.done += 1;
+                    self.failed.push(entry_id);
+                }
+            }
+        }
+        if self.tasks.is_empty() && self.queue.is_empty() && self.running.is_some() {
+            let action = self.running.take().unwrap();
+            if action == BatchAction::Delete {
+                self.undo = self.batch.iter().filter(|id| !self.failed.contains(id)).copied().collect();
+            }
+            self.report = Some(if self.failed.is_empty() {
+                format!("{}: all {} done", action.label(), self.total)
+            } else {
+                log::info!("batch failed for entries {:?}", self.failed);
+                format!(
+                    "{}: {} succeeded, {} failed",
+                    action.label(),
+                    self.total - self.failed.len(),
+                    self.failed.len()
+                )
+            });
+            if !self.failed.is_empty() {
+                self.retry = Some(action);
+            }
+            self.props.done_callback.emit(());
+        }
+    }
+
+    fn view_progress(&self) -> Html {
+        match (&self.running, &self.report) {
+            (Some(action), _) => html! {
+                <span class="batch-progress">
+                    <progress max=self.total.to_string() value=self.done.to_string()/>
+                    { format!(" {} {}/{}", action.label(), self.done, self.total) }
+                </span>
+            },
+            (None, Some(report)) => {
+                let class = if self.failed.is_empty() { "batch-report" } else { "batch-report batch-failed" };
+                let undo = if self.undo.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <button class="batch-button batch-undo" onclick=self.link.callback(|_| BatchMsg::Undo)>
+                            {"Undo"}
+                        </button>
+                    }
+                };
+                let retry = if self.retry.is_none() {
+                    html! {}
+                } else {
+                    html! {
+                        <button class="batch-button batch-undo" onclick=self.link.callback(|_| BatchMsg::Retry)>
+                            {"Retry failed"}
+                        </button>
+                    }
+                };
+                html! { <span class=class>{ report }{ retry }{ undo }</span> }
+            }
+            (None, None) => html! {},
+        }
+    }
+}
+
+impl Component for BatchBar {
+    type Message = BatchMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            export_href: export_href(&props.entries),
+            link,
+            props,
+            tag: String::from(""),
+            running: None,
+            batch: vec![],
+            queue: VecDeque::new(),
+            tasks: vec![],
+            total: 0,
+            done: 0,
+            failed: vec![],
+            report: None,
+            undo: vec![],
+            retry: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.entries != props.entries {
+            self.export_href = export_href(&props.entries);
+        }
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            BatchMsg::TagEdit(tag) => {
+                self.tag = tag;
+                true
+            }
+            BatchMsg::Run(action) => {
+                if self.running.is_some() || self.props.entries.is_empty() {
+                    return false;
+                }
+                let entry_ids = self.props.entries.iter().map(|entry| entry.entry_id).collect();
+                self.start(action, entry_ids);
+                true
+            }
+            BatchMsg::Undo => {
+                if self.running.is_some() || self.undo.is_empty() {
+                    return false;
+                }
+                let entry_ids = std::mem::take(&mut self.undo);
+                self.start(BatchAction::Restore, entry_ids);
+                true
+            }
+            BatchMsg::Retry => {
+                if self.running.is_some() {
+                    return false;
+                }
+                match self.retry.take() {
+                    Some(action) => {
+                        let entry_ids = std::mem::take(&mut self.failed);
+                        // what the first run archived can still be undone
+                        let archived = std::mem::take(&mut self.undo);
+                        self.start(action, entry_ids);
+                        self.undo = archived.into_iter().chain(self.undo.drain(..)).collect();
+                        true
+                    }
+                    None => false,
+                }
+            }
+            BatchMsg::Sent(entry_id, success) => {
+                self.tasks.retain(|(id, _)| *id != entry_id);
+                self.done += 1;
+                if !success {
+                    self.failed.push(entry_id);
+                }
+                self.pump();
+                true
+            }
+            BatchMsg::Clear => {
+                self.report = None;
+                self.undo = vec![];
+                self.retry = None;
+                self.props.clear_callback.emit(());
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let tag = self.tag.trim().to_string();
+        let remove_tag = tag.clone();
+        let busy = self.running.is_some();
+        html! {
+            <div class="batch-bar shadow-sm bg-white rounded">
+                <span class="batch-count">{ format!("{} selected", self.props.entries.len()) }</span>
+                <input type="text" class="batch-tag-input" placeholder="tag"
+                    value=self.tag.clone()
+                    oninput=self.link.callback(|e: InputData| BatchMsg::TagEdit(e.value))/>
+                <button class="batch-button" disabled=busy || tag.is_empty()
+                    onclick=self.link.callback(move |_| BatchMsg::Run(BatchAction::AddTag(tag.clone())))>
+                    {"Add tag"}
+                </button>
+                <button class="batch-button" disabled=busy || remove_tag.is_empty()
+                    onclick=self.link.callback(move |_| BatchMsg::Run(BatchAction::RemoveTag(remove_tag.clone())))>
+                    {"Remove tag"}
+                </button>
+                <button class="batch-button" disabled=busy
+                    onclick=self.link.callback(|_| BatchMsg::Run(BatchAction::Complete(true)))>
+                    {"Completed"}
+                </button>
+                <button class="batch-button" disabled=busy
+                    onclick=self.link.callback(|_| BatchMsg::Run(BatchAction::Complete(false)))>
+                    {"Uncompleted"}
+                </button>
+                <button class="batch-button" disabled=busy
+                    onclick=self.link.callback(|_| BatchMsg::Run(BatchAction::Recrawl))>
+                    {"Re-crawl"}
+                </button>
+                <a class="batch-button" download="openmemex-export.json" href=self.export_href.clone()>
+                    {"Export"}
+                </a>
+                <button class="batch-button batch-delete" disabled=busy
+                    onclick=self.link.callback(|_| BatchMsg::Run(BatchAction::Delete))>
+                    {"Delete"}
+                </button>
+                <button class="batch-button" disabled=busy onclick=self.link.callback(|_| BatchMsg::Clear)>
+                    {"Clear"}
+                </button>
+                { self.view_progress() }
+            </div>
+        }
+    }
+}
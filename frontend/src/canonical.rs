@@ -0,0 +1,103 @@
+use url::Url;
+
+// query parameters that only track where a link was shared from
+const TRACKING_PARAMS: [&str; 9] = [
+    "fbclid", "gclid", "dclid", "msclkid", "igshid", "mc_cid", "mc_eid", "ref", "ref_src",
+];
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+// Comparable form of a url so the same page captured through different links
+// matches: no scheme, no `www.`, no tracking params, fragment or trailing slash.
+// Returns None for anything that isn't an http(s) url.
+pub fn canonical_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url.trim()).ok()?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return None;
+    }
+    let host = parsed.host_str()?.to_lowercase();
+    let host = host.trim_start_matches("www.");
+    // the crawler rewrites arxiv links to the export mirror
+    let host = if host == "export.arxiv.org" { "arxiv.org" } else { host };
+    let path = parsed.path().trim_end_matches('/');
+    // a paper's pdf and its abstract page are the same entry
+    let arxiv_pdf = match path.strip_prefix("/pdf/") {
+        Some(paper) if host == "arxiv.org" => Some(format!("/abs/{}", paper.trim_end_matches(".pdf"))),
+        _ => None,
+    };
+    let path = arxiv_pdf.as_deref().unwrap_or(path);
+    let query: Vec<String> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    if query.is_empty() {
+        Some(format!("{}{}", host, path))
+    } else {
+        Some(format!("{}{}?{}", host, path, query.join("&")))
+    }
+}
+
+// Part of the canonical url to send to `/duplicates`, which does a substring
+// match so the stored url may still carry `www.` or tracking params.
+// Arxiv papers are looked up by id, stored as either the pdf or the abstract.
+pub fn search_key(canonical: &str) -> &str {
+    let key = canonical.split('?').next().unwrap_or(canonical);
+    key.strip_prefix("arxiv.org/abs/").unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(url: &str) -> String {
+        canonical_url(url).unwrap()
+    }
+
+    #[test]
+    fn scheme_and_www_are_dropped() {
+        assert_eq!(canonical("https://www.example.com/page"), "example.com/page");
+        assert_eq!(canonical("http://example.com/page"), "example.com/page");
+        assert_eq!(canonical("https://WWW.Example.com/page"), "example.com/page");
+    }
+
+    #[test]
+    fn tracking_params_are_removed() {
+        assert_eq!(
+            canonical("https://example.com/post?utm_source=feed&utm_medium=rss&id=7"),
+            "example.com/post?id=7"
+        );
+        assert_eq!(canonical("https://example.com/post?fbclid=abc123"), "example.com/post");
+        assert_eq!(canonical("https://example.com/post?ref=hn&gclid=x"), "example.com/post");
+    }
+
+    #[test]
+    fn trailing_slash_and_fragment_are_dropped() {
+        assert_eq!(canonical("https://example.com/docs/"), "example.com/docs");
+        assert_eq!(canonical("https://example.com/docs#install"), "example.com/docs");
+        assert_eq!(canonical("https://example.com/"), "example.com");
+    }
+
+    #[test]
+    fn arxiv_pdf_and_abstract_match() {
+        assert_eq!(canonical("https://arxiv.org/abs/2106.01234"), "arxiv.org/abs/2106.01234");
+        assert_eq!(canonical("https://arxiv.org/pdf/2106.01234.pdf"), "arxiv.org/abs/2106.01234");
+        assert_eq!(canonical("https://arxiv.org/pdf/2106.01234"), "arxiv.org/abs/2106.01234");
+        assert_eq!(canonical("http://export.arxiv.org/abs/2106.01234"), "arxiv.org/abs/2106.01234");
+    }
+
+    #[test]
+    fn only_web_urls_have_a_canonical_form() {
+        assert_eq!(canonical_url("just a note"), None);
+        assert_eq!(canonical_url("ftp://example.com/file"), None);
+        assert_eq!(canonical_url("javascript:alert(1)"), None);
+    }
+
+    #[test]
+    fn search_key_leaves_out_the_query() {
+        assert_eq!(search_key("example.com/post?id=7"), "example.com/post");
+        assert_eq!(search_key("arxiv.org/abs/2106.01234"), "2106.01234");
+    }
+}
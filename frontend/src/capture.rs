@@ -0,0 +1,235 @@
+use crate::api::*;
+use crate::app_router::query_param;
+use crate::sync::*;
+use yew::agent::{Bridge, Bridged};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::{
+    format::{Json, Nothing},
+    prelude::*,
+    utils::{host, window},
+};
+
+#[derive(Debug)]
+pub enum CaptureMsg {
+    ReceiveTags(Result<Vec<String>, anyhow::Error>),
+    TagEdit(String),
+    TagKeyDown(KeyboardEvent),
+    RemoveTag(String),
+    Submit,
+    SyncStatus(SyncStatus),
+}
+
+// Minimal AddNote opened by the bookmarklet:
+// /frontend/capture?url=...&title=...&selection=...
+pub struct Capture {
+    link: ComponentLink<Self>,
+    url: Option<String>,
+    title: Option<String>,
+    selection: Option<String>,
+    tag: String,
+    tags: Vec<String>,
+    exist_tags: Vec<String>,
+    submitted: bool,
+    // outbox key of the capture, the window closes once it is sent
+    key: Option<String>,
+    // the server refused the capture
+    rejected: bool,
+    tag_task: Option<FetchTask>,
+    sync: Box<dyn Bridge<SyncAgent>>,
+}
+
+impl Capture {
+    fn annotation(&self) -> Option<String> {
+        let selection = self.selection.as_ref()?;
+        let quoted = selection
+            .lines()
+            .map(|line| format!("> {}", line))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let title = self.title.clone().unwrap_or_default();
+        Some(format!("# Notes on {}\n\n{}\n", title, quoted))
+    }
+
+    fn add_tag(&mut self) {
+        let tag = self.tag.trim().trim_end_matches(',').to_string();
+        if !tag.is_empty() && !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self.tag = String::from("");
+    }
+}
+
+impl Component for Capture {
+    type Message = CaptureMsg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let server = host().unwrap();
+        let request = Request::get(format!("http://{}/all/tags", server))
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = link.callback_once(
+            |response: Response<Json<Result<Vec<String>, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                CaptureMsg::ReceiveTags(data)
+            },
+        );
+        let tag_task = FetchService::fetch(request, callback).ok();
+        let sync = SyncAgent::bridge(link.callback(CaptureMsg::SyncStatus));
+        Self {
+            url: query_param("url"),
+            title: query_param("title"),
+            selection: query_param("selection"),
+            tag: String::from(""),
+            tags: vec![],
+            exist_tags: vec![],
+            submitted: false,
+            key: None,
+            rejected: false,
+            tag_task,
+            sync,
+            link,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> bool {
+        false
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        match msg {
+            CaptureMsg::ReceiveTags(response) => {
+                self.tag_task = None;
+                match response {
+                    Ok(result) => self.exist_tags = result,
+                    Err(error) => log::info!("capture tag receive error: {}", error),
+                }
+                true
+            }
+            CaptureMsg::TagEdit(content) => {
+                self.tag = content;
+                if self.tag.ends_with(',') {
+                    self.add_tag();
+                    return true;
+                }
+                false
+            }
+            CaptureMsg::TagKeyDown(keypress) => {
+                if keypress.key() == "Enter" {
+                    if self.tag.trim().is_empty() {
+                        self.link.send_message(CaptureMsg::Submit);
+                    } else {
+                        self.add_tag();
+                    }
+                    return true;
+                }
+                false
+            }
+            CaptureMsg::RemoveTag(tag_name) => {
+                self.tags.retain(|t| *t != tag_name);
+                true
+            }
+            CaptureMsg::Submit => {
+                let url = match (&self.url, self.submitted) {
+                    (Some(url), false) => url.clone(),
+                    _ => return false,
+                };
+                // a tag still being typed counts too
+                self.add_tag();
+                let note = AddNotePayload {
+                    note_content: url,
+                    tags: self.tags.clone(),
+                };
+                log::info!("capturing {:?}", note);
+                let op = match self.annotation() {
+                    Some(annotation) => OutboxOp::Capture { note, annotation },
+                    None => OutboxOp::Note(note),
+                };
+                self.sync.send(SyncRequest::Enqueue(op));
+                self.submitted = true;
+                true
+            }
+            CaptureMsg::SyncStatus(SyncStatus::Queued(key)) => {
+                self.key = Some(key);
+                false
+            }
+            // closing earlier would leave the capture in the outbox of this
+            // window, to be posted a second time when the app opens next
+            CaptureMsg::SyncStatus(SyncStatus::Sent(key)) if self.key.as_ref() == Some(&key) => {
+                // only works for windows opened by script, which the bookmarklet does
+                if let Err(error) = window().close() {
+                    log::info!("could not close capture window: {:?}", error);
+                }
+                false
+            }
+            CaptureMsg::SyncStatus(SyncStatus::Rejected(item, _)) if self.key.as_ref() == Some(&item.key) => {
+                self.rejected = true;
+                true
+            }
+            CaptureMsg::SyncStatus(_) => false,
+        }
+    }
+
+    fn view(&self) -> Html {
+        let url = match &self.url {
+            Some(url) => url.clone(),
+            None => {
+                return html! { <div class="capture">{"Nothing to capture - open this page from the bookmarklet."}</div> }
+            }
+        };
+        let title = self.title.clone().unwrap_or_else(|| url.clone());
+        html! {
+            <div class="capture">
+                <h5>{ title }</h5>
+                <font color="grey">{ url }</font>
+                {
+                    match &self.selection {
+                        Some(selection) => html! { <blockquote class="capture-selection">{ selection }</blockquote> },
+                        None => html! {},
+                    }
+                }
+                <input type="text" class="capture-tag-input shadow-sm p-3 mb-3 bg-white rounded" list="capture-tags"
+                    placeholder="tags (enter or comma to add)"
+                    value=self.tag.clone()
+                    oninput=self.link.callback(|e: InputData| CaptureMsg::TagEdit(e.value))
+                    onkeydown=self.link.callback(CaptureMsg::TagKeyDown)
+                />
+                <datalist id="capture-tags">
+                    { for self.exist_tags.iter().map(|tag| html! { <option value=tag.clone()/> }) }
+                </datalist>
+                <div class="tags-list-div">
+                {
+                    for self.tags.iter().map(|curr_tag| {
+                        let tag_name = curr_tag.clone();
+                        html! {
+                            <div class="topic-tag-addnote" title="remove"
+                                onclick=self.link.callback(move |_| CaptureMsg::RemoveTag(tag_name.clone()))>
+                                { curr_tag }
+                            </div>
+                        }
+                    })
+                }
+                </div>
+                <button class="capture-submit shadow-sm p-3 mb-3 bg-white rounded" disabled=self.submitted
+                    onclick=self.link.callback(|_| CaptureMsg::Submit)>
+                    {
+                        match (self.submitted, self.rejected) {
+                            (false, _) => "Capture",
+                            (true, false) => "Saving...",
+                            (true, true) => "Not saved",
+                        }
+                    }
+                </button>
+                {
+                    if self.rejected {
+                        html! { <div class="capture-status">{"The server refused this capture."}</div> }
+                    } else if self.submitted {
+                        html! { <div class="capture-status">{"Saving - this window closes once the server has the page."}</div> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+}
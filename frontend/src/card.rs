@@ -0,0 +1,421 @@
+use crate::api::*;
+use crate::app_router::*;
+use pulldown_cmark::{html::push_html, Event, Parser, Tag};
+use url::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use std::collections::HashMap;
+use web_sys::{IntersectionObserver, IntersectionObserverEntry, IntersectionObserverInit};
+use yew::format::{Json, Text};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::storage::{Area, StorageService};
+use yew::utils::{document, host};
+use yew::virtual_dom::VNode;
+use yew_router::prelude::*;
+
+pub type Link = RouterAnchor<AppRoute>;
+
+// A single gallery card. Hover is kept here so moving the mouse over the
+// gallery only re-renders the card under it.
+
+// thumbnails start loading this far before they scroll into view
+const THUMBNAIL_MARGIN: &str = "300px";
+// a note card shows this much of the note, cut at a line end where it can
+const EXCERPT_LENGTH: usize = 300;
+const EXCERPT_LINES: usize = 8;
+// entry id -> when a re-crawl was last asked for, shared by all tabs
+const RECRAWLED_KEY: &str = "openmemex.recrawled";
+// a thumbnail still broken after this long is asked for again
+const RECRAWL_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+type Observer = (IntersectionObserver, Closure<dyn FnMut(js_sys::Array)>);
+
+#[derive(Debug)]
+pub enum CardMsg {
+    Hover(bool),
+    // the thumbnail area came near the viewport
+    Visible,
+    ThumbnailFailed,
+}
+
+pub struct Card {
+    link: ComponentLink<Self>,
+    props: Props,
+    hovered: bool,
+    visible: bool,
+    // the thumbnail is missing or broken, the placeholder stays
+    failed: bool,
+    thumbnail: NodeRef,
+    observer: Option<Observer>,
+    // the thumbnail changed, watch for it again after the next render
+    reobserve: bool,
+    // kept so hovering a note card doesn't rebuild its markdown
+    note_body: Option<web_sys::Element>,
+    recrawl_task: Option<FetchTask>,
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct Props {
+    pub item: Cache,
+    // position in the gallery, for selection ranges
+    pub index: usize,
+    pub selected: bool,
+    // the entry open in Detail
+    pub open: bool,
+    // clicks on the image select instead of opening
+    pub selecting: bool,
+    // shown on note cards
+    pub tags: Vec<String>,
+    pub annotations: usize,
+    pub click_callback: Callback<(MouseEvent, usize, Cache)>,
+    pub select_callback: Callback<(MouseEvent, usize)>,
+}
+
+// display name of the site a captured page came from, empty for notes
+pub fn site_name(item: &Cache) -> String {
+    item.url
+        .as_ref()
+        .and_then(|url| Url::parse(url).ok())
+        .and_then(|url| url.host_str().map(host_simplify))
+        .unwrap_or_default()
+}
+
+fn host_simplify(url: &str) -> String {
+    // TODO - don't hard code this
+    match url {
+        "export.arxiv.org" => "Arxiv".to_string(),
+        "www.arxiv.org" => "Arxiv".to_string(),
+        "www.github.com" => "Github".to_string(),
+        "github.com" => "Github".to_string(),
+        "medium.com" => "Medium".to_string(),
+        "www.reddit.com" => "Reddit".to_string(),
+        "twitter.com" => "Twitter".to_string(),
+        "www.youtube.com" => "YouTube".to_string(),
+        _ => url.to_string(),
+    }
+}
+
+// a stable colour per site so tiles from the same site look alike
+fn site_hue(site: &str) -> u32 {
+    site.bytes().fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32)) % 360
+}
+
+// stands in for a missing or broken thumbnail, a coloured tile for the site
+fn placeholder(item: &Cache) -> Html {
+    let site = site_name(item);
+    html! {
+        <div class="card-placeholder card-placeholder-site"
+            style=format!("background: hsl({}, 45%, 55%)", site_hue(&site))>
+            { site }
+        </div>
+    }
+}
+
+// http(s) or relative, nothing a click or an image load could run
+fn safe_url(url: &str) -> bool {
+    let url = url.trim().to_lowercase();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => url.starts_with("http:") || url.starts_with("https:"),
+        _ => true,
+    }
+}
+
+// the start of a note rendered as markdown, raw html is shown as text;
+// links keep only their text since the card itself is a link to Detail,
+// images with other than web urls only their alt text
+fn markdown_excerpt(content: &str) -> web_sys::Element {
+    let mut excerpt = String::new();
+    for line in content.lines().take(EXCERPT_LINES) {
+        if excerpt.len() + line.len() > EXCERPT_LENGTH {
+            if excerpt.is_empty() {
+                excerpt = line.chars().take(EXCERPT_LENGTH).collect();
+            }
+            excerpt.push_str("...");
+            break;
+        }
+        excerpt.push_str(line);
+        excerpt.push('\n');
+    }
+    let events = Parser::new(&excerpt).filter_map(|event| match event {
+        Event::Html(html) => Some(Event::Text(html)),
+        Event::Start(Tag::Link(..)) | Event::End(Tag::Link(..)) => None,
+        Event::Start(Tag::Image(_, ref url, _)) | Event::End(Tag::Image(_, ref url, _)) if !safe_url(url) => None,
+        event => Some(event),
+    });
+    let mut rendered = String::new();
+    push_html(&mut rendered, events);
+    let element = document().create_element("div").unwrap();
+    element.set_class_name("card-note-body");
+    element.set_inner_html(&rendered);
+    element
+}
+
+// notes have no page to show, their own text stands in for it
+fn note_body(item: &Cache) -> Option<web_sys::Element> {
+    match item.url {
+        Some(_) => None,
+        None => Some(markdown_excerpt(&item.content.clone().unwrap_or_default())),
+    }
+}
+
+// Asks the crawler to make a missing thumbnail again. Nothing waits on the
+// answer and it isn't retried, a card that still fails asks again a day later.
+fn recrawl(entry_id: i32) -> Option<FetchTask> {
+    let mut storage = StorageService::new(Area::Local).ok()?;
+    let Json(recrawled) = storage.restore(RECRAWLED_KEY);
+    let mut recrawled: HashMap<i32, f64> = recrawled.unwrap_or_default();
+    let now = js_sys::Date::now();
+    if recrawled.get(&entry_id).is_some_and(|at| now - at < RECRAWL_MS) {
+        return None;
+    }
+    recrawled.retain(|_, at| now - *at < RECRAWL_MS);
+    recrawled.insert(entry_id, now);
+    storage.store(RECRAWLED_KEY, Json(&recrawled));
+
+    let query = format!("http://{}/submit/recrawl", host().unwrap());
+    let body = RecrawlPayload { entry_id };
+    let request = Request::post(query)
+        .header("Content-Type", "application/json")
+        .body(Json(&body))
+        .expect("Could not build request.");
+    let callback = Callback::from(move |response: Response<Text>| {
+        log::info!("recrawl of {} answered {}", entry_id, response.status());
+    });
+    FetchService::fetch(request, callback).ok()
+}
+
+impl Card {
+    fn has_thumbnail(&self) -> bool {
+        self.props.item.thumbnail_file.as_ref().is_some_and(|file| !file.is_empty())
+    }
+
+    fn observe(&mut self) {
+        let element = match self.thumbnail.cast::<web_sys::Element>() {
+            Some(element) => element,
+            None => return,
+        };
+        let link = self.link.clone();
+        let callback = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+            let visible = entries
+                .iter()
+                .any(|entry| entry.unchecked_into::<IntersectionObserverEntry>().is_intersecting());
+            if visible {
+                link.send_message(CardMsg::Visible);
+            }
+        }) as Box<dyn FnMut(js_sys::Array)>);
+        let options = IntersectionObserverInit::new();
+        options.set_root_margin(THUMBNAIL_MARGIN);
+        match IntersectionObserver::new_with_options(callback.as_ref().unchecked_ref(), &options) {
+            Ok(observer) => {
+                observer.observe(&element);
+                self.observer = Some((observer, callback));
+            }
+            // no observer support, load right away
+            Err(_) => self.link.send_message(CardMsg::Visible),
+        }
+    }
+
+    fn unobserve(&mut self) {
+        if let Some((observer, _)) = self.observer.take() {
+            observer.disconnect();
+        }
+    }
+
+    fn view_note(
+        &self,
+        div_class: &str,
+        body: &web_sys::Element,
+        callback_click: Callback<MouseEvent>,
+        callback_select: Callback<MouseEvent>,
+    ) -> Html {
+        let item = &self.props.item;
+        let body = VNode::VRef(body.clone().into());
+        let annotations = match self.props.annotations {
+            0 => String::new(),
+            1 => "1 annotation".to_string(),
+            n => format!("{} annotations", n),
+        };
+        html! {
+            <div class=format!("card-note {}", div_class) onclick=callback_click
+                onmouseover=self.link.callback(|_| CardMsg::Hover(true))
+                onmouseleave=self.link.callback(|_| CardMsg::Hover(false))>
+                <input type="checkbox" class="card-select" checked=self.props.selected onclick=callback_select
+                    title="select (shift-click for a range)"/>
+                { &item.date }
+                <font color="grey">{ "Note" }</font>
+                <hr/>
+                {
+                    if self.props.selecting {
+                        body
+                    } else {
+                        html! { <Link route=AppRoute::DetailById(item.entry_id)>{ body }</Link> }
+                    }
+                }
+                <div class="card-note-footer">
+                    { for self.props.tags.iter().map(|tag| html! { <span class="card-note-tag">{ tag }</span> }) }
+                    <span class="card-note-annotations">{ annotations }</span>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_thumbnail(&self, img_class: &'static str) -> Html {
+        let item = &self.props.item;
+        if self.failed || !self.has_thumbnail() {
+            return placeholder(item);
+        }
+        if !self.visible {
+            return html! { <div class="card-placeholder card-placeholder-loading"/> };
+        }
+        let thumbnail_file = item.thumbnail_file.clone().unwrap_or_default();
+        html! {
+            <img src=thumbnail_file style="width: 60%;" class=img_class
+                onerror=self.link.callback(|_| CardMsg::ThumbnailFailed)/>
+        }
+    }
+}
+
+impl Component for Card {
+    type Message = CardMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            note_body: note_body(&props.item),
+            link,
+            props,
+            hovered: false,
+            visible: false,
+            failed: false,
+            thumbnail: NodeRef::default(),
+            observer: None,
+            reobserve: false,
+            recrawl_task: None,
+        }
+    }
+
+    // the gallery re-renders every card it shows, only changed ones redraw
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+        if self.props.item.thumbnail_file != props.item.thumbnail_file {
+            self.failed = false;
+            self.visible = false;
+            self.unobserve();
+            self.reobserve = true;
+        }
+        if self.props.item.content != props.item.content || self.props.item.url != props.item.url {
+            self.note_body = note_body(&props.item);
+        }
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            CardMsg::Hover(hovered) => {
+                let changed = self.hovered != hovered;
+                self.hovered = hovered;
+                changed
+            }
+            CardMsg::Visible => {
+                self.unobserve();
+                self.visible = true;
+                true
+            }
+            CardMsg::ThumbnailFailed => {
+                self.failed = true;
+                // the crawler makes the screenshot and thumbnail again
+                if self.props.item.url.is_some() {
+                    self.recrawl_task = recrawl(self.props.item.entry_id);
+                }
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if (first_render || self.reobserve) && self.has_thumbnail() {
+            self.observe();
+        }
+        self.reobserve = false;
+    }
+
+    fn destroy(&mut self) {
+        self.unobserve();
+    }
+
+    fn view(&self) -> Html {
+        let item = &self.props.item;
+        let index = self.props.index;
+
+        let img_class = if self.hovered {
+            "card-img-foreground shadow-sm bg-white rounded"
+        } else {
+            "card-img-background shadow-sm bg-white rounded"
+        };
+
+        let div_class = if self.props.selected {
+            "card card-selected shadow-sm p-3 mb-5 bg-light rounded"
+        } else if self.props.open {
+            "card shadow-none p-3 mb-5 bg-light rounded"
+        } else if self.hovered {
+            "card-highlight card shadow p-3 mb-5 bg-white rounded"
+        } else {
+            "card shadow-sm p-3 mb-5 bg-white rounded"
+        };
+
+        let click_item = item.clone();
+        let callback_click = self.props.click_callback.reform(move |m| (m, index, click_item.clone()));
+        let callback_select = self.props.select_callback.reform(move |m: MouseEvent| {
+            m.stop_propagation();
+            (m, index)
+        });
+        if let Some(body) = self.note_body.as_ref() {
+            return self.view_note(div_class, body, callback_click, callback_select);
+        }
+        let img = html! {
+            <div ref=self.thumbnail.clone() class="card-thumbnail">{ self.view_thumbnail(img_class) }</div>
+        };
+        let mut content = item.content.clone().unwrap_or("".to_owned());
+        let max_length = 70;
+        if content.len() > max_length {
+            content = content.chars().take(max_length).collect::<String>();
+            content.push_str("...");
+        }
+        html! {
+            <div class={ div_class } onclick=callback_click
+                onmouseover=self.link.callback(|_| CardMsg::Hover(true))
+                onmouseleave=self.link.callback(|_| CardMsg::Hover(false))>
+                <input type="checkbox" class="card-select" checked=self.props.selected onclick=callback_select
+                    title="select (shift-click for a range)"/>
+                { &item.date }
+                <font color="grey">{ site_name(item) }</font>
+                <hr/>
+                <center>
+                {
+                    if self.props.selecting {
+                        img
+                    } else {
+                        html! { <Link route=AppRoute::DetailById(item.entry_id)>{ img }</Link> }
+                    }
+                }
+                </center>
+                <center>
+                    {
+                        match item.url.as_ref() {
+                            Some(url) => html! {
+                                <a href={ url.to_string() }> { content } </a>
+                            },
+                            None => html! {
+                                { content }
+                            }
+                        }
+                    }
+                </center>
+            </div>
+        }
+    }
+}
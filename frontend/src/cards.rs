@@ -1,144 +1,742 @@
 use crate::api::*;
+use crate::batch::*;
+use crate::card::*;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use url::*;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use yew::format::{Json, Nothing};
 use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::storage::{Area, StorageService};
+use yew::utils::{document, host, window};
+use yew::web_sys::HtmlElement;
 use crate::app_router::*;
-use yew_router::prelude::*;
 
-pub type Link = RouterAnchor<AppRoute>;
+// the gallery layout and grouping are kept between visits
+const GALLERY_LAYOUT_KEY: &str = "openmemex.gallery_layout";
+const GALLERY_GROUP_KEY: &str = "openmemex.gallery_group";
+
+// only the rows in view are mounted, plus this many above and below
+const BUFFER_ROWS: usize = 3;
+// columns of the grid layout until .cards is rendered and can be measured
+const GRID_COLUMNS: usize = 4;
+// close enough to .cards-group-header for placing the spacers
+const HEADER_HEIGHT: f64 = 36.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GalleryLayout {
+    // thumbnail cards
+    Grid,
+    // one line per entry with date, site, title and tags
+    List,
+    // sortable columns
+    Table,
+}
+
+impl GalleryLayout {
+    fn label(&self) -> &str {
+        match self {
+            GalleryLayout::Grid => "Grid",
+            GalleryLayout::List => "List",
+            GalleryLayout::Table => "Table",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GalleryGroup {
+    Ungrouped,
+    Day,
+    Week,
+    Month,
+}
+
+impl GalleryGroup {
+    fn label(&self) -> &str {
+        match self {
+            GalleryGroup::Ungrouped => "Ungrouped",
+            GalleryGroup::Day => "By day",
+            GalleryGroup::Week => "By week",
+            GalleryGroup::Month => "By month",
+        }
+    }
+
+    // first day of the period a date falls in, which also keys the group, and its heading
+    fn period(&self, date: &str) -> (String, String) {
+        let day = match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(day) => day,
+            Err(_) => return (date.to_string(), date.to_string()),
+        };
+        let (start, label) = match self {
+            GalleryGroup::Ungrouped | GalleryGroup::Day => (day, day.format("%a %b %-d, %Y").to_string()),
+            GalleryGroup::Week => {
+                let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+                (monday, monday.format("Week of %b %-d, %Y").to_string())
+            }
+            GalleryGroup::Month => (day.with_day(1).unwrap_or(day), day.format("%B %Y").to_string()),
+        };
+        (start.format("%Y-%m-%d").to_string(), label)
+    }
+}
+
+// consecutive entries in the same period, entries keep their gallery index
+struct EntryGroup<'a> {
+    key: String,
+    label: String,
+    items: Vec<(usize, &'a Cache)>,
+}
+
+// a run of gallery rows under an optional group header, windowed on its own
+struct Section<'a> {
+    group: Option<EntryGroup<'a>>,
+    // entries in the section, shown on the header even when collapsed
+    count: usize,
+    // index of the first row among the rows of all sections
+    start: usize,
+    rows: Vec<Vec<(usize, &'a Cache)>>,
+}
+
+fn group_entries(entries: &[Cache], group: GalleryGroup) -> Vec<EntryGroup<'_>> {
+    let mut groups: Vec<EntryGroup> = vec![];
+    for (index, item) in entries.iter().enumerate() {
+        let (key, label) = group.period(&item.date);
+        match groups.last_mut() {
+            Some(last) if last.key == key => last.items.push((index, item)),
+            _ => groups.push(EntryGroup { key, label, items: vec![(index, item)] }),
+        }
+    }
+    groups
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableColumn {
+    Date,
+    Time,
+    Site,
+    Title,
+    Tags,
+    Completed,
+}
+
+impl TableColumn {
+    fn label(&self) -> &str {
+        match self {
+            TableColumn::Date => "Date",
+            TableColumn::Time => "Time",
+            TableColumn::Site => "Site",
+            TableColumn::Title => "Title",
+            TableColumn::Tags => "Tags",
+            TableColumn::Completed => "Completed",
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum CardsMsg {
-    CardMouseOver(MouseEvent, i32),
-    CardClick(MouseEvent, i32, Cache),
+    CardClick(MouseEvent, usize, Cache),
+    SelectClick(MouseEvent, usize),
+    SelectAll,
+    ClearSelection,
+    // tags or completion may have changed even if the entries didn't
+    BatchDone,
+    Layout(GalleryLayout),
+    // sorts the table, again on the same column reverses it
+    SortBy(TableColumn),
+    ReceiveEntryTags(Result<Vec<EntryTag>, anyhow::Error>),
+    ReceiveCompleted(Result<Vec<i32>, anyhow::Error>),
+    ReceiveAnnotationCounts(Result<Vec<AnnotationCount>, anyhow::Error>),
+    Group(ChangeData),
+    ToggleGroup(String),
+    JumpTo(ChangeData),
+    // the page scrolled or resized, the mounted rows may change
+    Scroll,
 }
 
-#[derive(Debug)]
 pub struct Cards {
     pub link: ComponentLink<Self>,
     pub entries: Option<Vec<Cache>>,
-    pub entry_id_mouseover: Option<i32>,
     pub entry_id_click: Option<i32>,
     pub card_click_callback: Callback<Option<Cache>>,
+    pub batch_done_callback: Callback<()>,
+    // multi-select for batch actions, the batch bar shows while selecting
+    pub selected: HashSet<i32>,
+    pub selecting: bool,
+    // index the next shift-click range starts from
+    pub anchor: Option<usize>,
+    layout: GalleryLayout,
+    group: GalleryGroup,
+    // keys of the groups folded away
+    collapsed: HashSet<String>,
+    // group to bring into view once rendered
+    scroll_to: Option<String>,
+    storage: Option<StorageService>,
+    sort: TableColumn,
+    ascending: bool,
+    // tags for every layout, completion for the table, annotation counts
+    // for the note cards of the grid
+    entry_tags: HashMap<i32, Vec<String>>,
+    completed: HashSet<i32>,
+    annotation_counts: HashMap<i32, usize>,
+    tags_task: Option<FetchTask>,
+    completed_task: Option<FetchTask>,
+    counts_task: Option<FetchTask>,
+    // made once so cards can tell when their props really changed
+    click_callback: Callback<(MouseEvent, usize, Cache)>,
+    select_callback: Callback<(MouseEvent, usize)>,
+    // rows mounted, first inclusive and last exclusive
+    rows: (usize, usize),
+    // measured height of a row, spacers stand in for the rows not mounted
+    row_height: f64,
+    // tracks of .cards as laid out by the stylesheet
+    grid_columns: usize,
+    container: NodeRef,
+    mounted: NodeRef,
+    scroll_listener: Closure<dyn FnMut()>,
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
     pub entries: Option<Vec<Cache>>,
     pub card_click_callback: Callback<Option<Cache>>,
+    #[prop_or_default]
+    pub batch_done_callback: Callback<()>,
+    // the entry open in Detail
+    #[prop_or_default]
+    pub selected_id: Option<i32>,
 }
 
-fn host_simplify(url: &str) -> String {
-    // TODO - don't hard code this
-    match url {
-        "export.arxiv.org" => "Arxiv".to_string(),
-        "www.arxiv.org" => "Arxiv".to_string(),
-        "www.github.com" => "Github".to_string(),
-        "github.com" => "Github".to_string(),
-        "medium.com" => "Medium".to_string(),
-        "www.reddit.com" => "Reddit".to_string(),
-        "twitter.com" => "Twitter".to_string(),
-        "www.youtube.com" => "YouTube".to_string(),
-        _ => url.to_string(),
+// distance from the top of the page
+fn page_top(element: &HtmlElement) -> f64 {
+    let mut top = element.offset_top() as f64;
+    let mut parent = element.offset_parent();
+    while let Some(element) = parent.and_then(|e| e.dyn_into::<HtmlElement>().ok()) {
+        top += element.offset_top() as f64;
+        parent = element.offset_parent();
     }
+    top
+}
+
+fn title(item: &Cache) -> String {
+    item.content.clone().or_else(|| item.url.clone()).unwrap_or_default()
 }
 
 impl Cards {
-    fn view_card(
-        &self,
-        parsed: &Result<Url, url::ParseError>,
-        thumbnail_file: &String,
-        item: &Cache,
-    ) -> Html {
-        let img_style = "width: 60%;";
-        let item_clone = item.clone();
-
-        let img_class = if item.entry_id != self.entry_id_mouseover.unwrap_or(-1) {
-            "card-img-background shadow-sm bg-white rounded"
+    fn view_grid(&self, items: &[(usize, &Cache)]) -> Html {
+        html! {
+            <div class="cards">
+            {
+                for items.iter().map(|(index, item)| html! {
+                    <Card key=item.entry_id item=(*item).clone() index=*index
+                        selected=self.selected.contains(&item.entry_id)
+                        open=Some(item.entry_id) == self.entry_id_click
+                        selecting=self.selecting
+                        tags=self.entry_tags.get(&item.entry_id).cloned().unwrap_or_default()
+                        annotations=self.annotation_counts.get(&item.entry_id).copied().unwrap_or(0)
+                        click_callback=self.click_callback.clone()
+                        select_callback=self.select_callback.clone()/>
+                })
+            }
+            </div>
+        }
+    }
+
+    fn columns(&self) -> usize {
+        match self.layout {
+            GalleryLayout::Grid => self.grid_columns,
+            _ => 1,
+        }
+    }
+
+    // whether each section has a heading and how many rows it shows, enough
+    // to place the window without building the rows
+    fn section_sizes(&self) -> Vec<(bool, usize)> {
+        let entries = self.entries.as_deref().unwrap_or_default();
+        let columns = self.columns();
+        if self.layout == GalleryLayout::Table || self.group == GalleryGroup::Ungrouped {
+            return vec![(false, entries.len().div_ceil(columns))];
+        }
+        group_entries(entries, self.group)
+            .iter()
+            .map(|group| {
+                let rows = if self.collapsed.contains(&group.key) { 0 } else { group.items.len().div_ceil(columns) };
+                (true, rows)
+            })
+            .collect()
+    }
+
+    fn sections<'a>(&self, entries: &'a [Cache]) -> Vec<Section<'a>> {
+        // the table has its own ordering, groups would fight it
+        let runs = if self.layout == GalleryLayout::Table {
+            vec![(None::<EntryGroup>, self.displayed(entries).into_iter().enumerate().collect())]
+        } else if self.group == GalleryGroup::Ungrouped {
+            vec![(None, entries.iter().enumerate().collect())]
         } else {
-            "card-img-foreground shadow-sm bg-white rounded"
+            group_entries(entries, self.group)
+                .into_iter()
+                .map(|mut group| {
+                    let items = std::mem::take(&mut group.items);
+                    (Some(group), items)
+                })
+                .collect()
         };
+        let columns = self.columns();
+        let mut start = 0;
+        runs.into_iter()
+            .map(|(group, items)| {
+                let count = items.len();
+                let collapsed = group.as_ref().is_some_and(|group| self.collapsed.contains(&group.key));
+                let rows: Vec<Vec<(usize, &Cache)>> =
+                    if collapsed { vec![] } else { items.chunks(columns).map(|row| row.to_vec()).collect() };
+                let section = Section { group, count, start, rows };
+                start += section.rows.len();
+                section
+            })
+            .collect()
+    }
 
-        let div_class = if item.entry_id != self.entry_id_click.unwrap_or(-1) {
-            if item.entry_id != self.entry_id_mouseover.unwrap_or(-1) {
-                "card shadow-sm p-3 mb-5 bg-white rounded"
-            } else {
-                "card-highlight card shadow p-3 mb-5 bg-white rounded"
+    // rows the viewport shows, with the buffer around them
+    fn visible_rows(&self) -> (usize, usize) {
+        let container = match self.container.cast::<HtmlElement>() {
+            Some(container) => container,
+            None => return self.rows,
+        };
+        let window = window();
+        let scroll = window.scroll_y().unwrap_or(0.0);
+        let viewport = window.inner_height().ok().and_then(|h| h.as_f64()).unwrap_or(1000.0);
+        // where the gallery starts relative to the top of the viewport
+        let top = page_top(&container) - scroll;
+        let buffer = BUFFER_ROWS as f64 * self.row_height;
+        let (from, to) = (-top - buffer, viewport - top + buffer);
+        let (mut first, mut last) = (None, 0);
+        let (mut y, mut row) = (0.0, 0);
+        for (heading, rows) in self.section_sizes() {
+            if heading {
+                y += HEADER_HEIGHT;
             }
-        } else {
-            "card shadow-none p-3 mb-5 bg-light rounded"
+            for _ in 0..rows {
+                if y + self.row_height > from && y < to {
+                    first.get_or_insert(row);
+                    last = row + 1;
+                }
+                y += self.row_height;
+                row += 1;
+            }
+        }
+        (first.unwrap_or(last), last)
+    }
+
+    // the part of the mounted rows in a section, first inclusive and last exclusive
+    fn mounted_in(&self, start: usize, end: usize) -> (usize, usize) {
+        let first = self.rows.0.clamp(start, end);
+        (first, self.rows.1.clamp(first, end))
+    }
+
+    // rows in the element that measures the row height, see `view_entries`
+    fn measured_rows(&self) -> usize {
+        let mut start = 0;
+        for (_, rows) in self.section_sizes() {
+            let (first, last) = self.mounted_in(start, start + rows);
+            if first < last {
+                return last - first;
+            }
+            start += rows;
+        }
+        0
+    }
+
+    // the stylesheet decides how many cards fit in a row, rows are mounted
+    // again when that changes
+    fn measure_columns(&mut self) {
+        let grid = self
+            .container
+            .cast::<HtmlElement>()
+            .and_then(|container| container.query_selector(".cards").ok().flatten());
+        let grid = match grid {
+            Some(grid) if self.layout == GalleryLayout::Grid => grid,
+            _ => return,
         };
+        let columns = window()
+            .get_computed_style(&grid)
+            .ok()
+            .flatten()
+            .and_then(|style| style.get_property_value("grid-template-columns").ok())
+            .map(|tracks| tracks.split_whitespace().count())
+            .unwrap_or(0);
+        if columns > 0 && columns != self.grid_columns {
+            self.grid_columns = columns;
+            self.rows = (0, 0);
+        }
+    }
 
-        let callback_mouseover = |entry_id| {
-            self.link
-                .callback(move |m| CardsMsg::CardMouseOver(m, entry_id))
+    fn initial_row_height(&self) -> f64 {
+        match self.layout {
+            GalleryLayout::Grid => 320.0,
+            GalleryLayout::List => 30.0,
+            GalleryLayout::Table => 28.0,
+        }
+    }
+
+    fn view_items(&self, items: &[(usize, &Cache)]) -> Html {
+        match self.layout {
+            GalleryLayout::List => self.view_list(items),
+            _ => self.view_grid(items),
+        }
+    }
+
+    // the mounted rows of a section between spacers as tall as the rest
+    fn view_section(&self, section: &Section, mounted: NodeRef) -> Html {
+        let end = section.start + section.rows.len();
+        let (first, last) = self.mounted_in(section.start, end);
+        let items: Vec<(usize, &Cache)> =
+            section.rows[first - section.start..last - section.start].iter().flatten().copied().collect();
+        let rows = html! {
+            <>
+                <div style=format!("height: {:.0}px", (first - section.start) as f64 * self.row_height)/>
+                <div ref=mounted>{ self.view_items(&items) }</div>
+                <div style=format!("height: {:.0}px", (end - last) as f64 * self.row_height)/>
+            </>
         };
+        let group = match &section.group {
+            Some(group) => group,
+            None => return rows,
+        };
+        let collapsed = self.collapsed.contains(&group.key);
+        let key = group.key.clone();
+        html! {
+            <div class="cards-group" id=format!("cards-group-{}", group.key)>
+                <div class="cards-group-header"
+                    onclick=self.link.callback(move |_| CardsMsg::ToggleGroup(key.clone()))>
+                    { if collapsed { "▸ " } else { "▾ " } }
+                    { &group.label }
+                    <span class="cards-group-count">{ section.count }</span>
+                </div>
+                { rows }
+            </div>
+        }
+    }
 
-        let callback_click = |entry_id| {
-            self.link
-                .callback(move |m| CardsMsg::CardClick(m, entry_id, item_clone.clone()))
+    fn view_entries(&self) -> Html {
+        let entries = match self.entries {
+            Some(ref entries) => entries,
+            None => return html! { <div> {"No Content"} </div> },
+        };
+        log::info!("{:#?} results fetched.", entries.len());
+        // the first section with mounted rows measures the row height
+        let mut measuring = true;
+        let mut mounted = |section: &Section| {
+            let (first, last) = self.mounted_in(section.start, section.start + section.rows.len());
+            if measuring && first < last {
+                measuring = false;
+                self.mounted.clone()
+            } else {
+                NodeRef::default()
+            }
+        };
+        let sections = self.sections(entries);
+        let body = if self.layout == GalleryLayout::Table {
+            html! { for sections.iter().map(|section| self.view_table(section, mounted(section))) }
+        } else {
+            html! { for sections.iter().map(|section| self.view_section(section, mounted(section))) }
         };
-        // log::info!("thumbnail file is {:?}", &thumbnail_file);
-        let mut content = item.content.clone().unwrap_or("".to_owned());
-        let max_length = 70;
-        if content.len() > max_length {
-            content = content.chars().take(max_length).collect::<String>();
-            content.push_str("...");
+        html! { <div ref=self.container.clone()>{ body }</div> }
+    }
+}
+
+impl Cards {
+    fn row_class(&self, entry_id: i32) -> &'static str {
+        if self.selected.contains(&entry_id) {
+            "cards-row cards-row-selected"
+        } else if Some(entry_id) == self.entry_id_click {
+            "cards-row cards-row-open"
+        } else {
+            "cards-row"
+        }
+    }
+
+    fn view_tags(&self, entry_id: i32) -> Html {
+        html! {
+            for self.entry_tags.get(&entry_id).into_iter().flatten().map(|tag| html! {
+                <span class="cards-row-tag">{ tag }</span>
+            })
         }
+    }
+
+    fn view_select(&self, entry_id: i32, index: usize) -> Html {
         html! {
-            <div class={ div_class } onmouseover=callback_mouseover(item.entry_id) onclick = callback_click(item.entry_id)>
-                { &item.date }
-                <font color="grey">
-                {
-                    match &parsed {
-                        Ok(x) => { host_simplify(x.host_str().unwrap()) }
-                        Err(error) => { "".to_string() }
+            <input type="checkbox" class="card-select" checked=self.selected.contains(&entry_id)
+                onclick=self.link.callback(move |m: MouseEvent| {
+                    m.stop_propagation();
+                    CardsMsg::SelectClick(m, index)
+                })
+                title="select (shift-click for a range)"/>
+        }
+    }
+
+    fn view_title(&self, item: &Cache) -> Html {
+        html! {
+            <Link route=AppRoute::DetailById(item.entry_id)>{ title(item) }</Link>
+        }
+    }
+
+    fn view_list(&self, items: &[(usize, &Cache)]) -> Html {
+        html! {
+            <div class="cards-list">
+            {
+                for items.iter().map(|(index, item)| {
+                    let index = *index;
+                    let click_item = (*item).clone();
+                    html! {
+                        <div class=self.row_class(item.entry_id)
+                            onclick=self.link.callback(move |m| CardsMsg::CardClick(m, index, click_item.clone()))>
+                            { self.view_select(item.entry_id, index) }
+                            <span class="cards-row-date">{ &item.date }</span>
+                            <span class="cards-row-site">{ site_name(item) }</span>
+                            <span class="cards-row-title">{ self.view_title(item) }</span>
+                            { self.view_tags(item.entry_id) }
+                        </div>
                     }
-                }
-                </font>
-                <hr/>
-                // <img src=thumbnail_file width="100%" style="height: 100px; overflow: hidden;"/>
-                <center>
+                })
+            }
+            </div>
+        }
+    }
 
-                    <Link route=AppRoute::Detail>
-                    <img src=thumbnail_file.to_string() style=img_style class=img_class/>
-                    </Link>
-                </center>
-                <center>
+    fn compare(&self, a: &Cache, b: &Cache) -> Ordering {
+        let tags = |item: &Cache| self.entry_tags.get(&item.entry_id).map(|tags| tags.join(" ")).unwrap_or_default();
+        let ordering = match self.sort {
+            TableColumn::Date => (&a.date, &a.time).cmp(&(&b.date, &b.time)),
+            TableColumn::Time => a.time.cmp(&b.time),
+            TableColumn::Site => site_name(a).cmp(&site_name(b)),
+            TableColumn::Title => title(a).to_lowercase().cmp(&title(b).to_lowercase()),
+            TableColumn::Tags => tags(a).cmp(&tags(b)),
+            TableColumn::Completed => self.completed.contains(&a.entry_id).cmp(&self.completed.contains(&b.entry_id)),
+        };
+        if self.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+
+    fn view_table(&self, section: &Section, mounted: NodeRef) -> Html {
+        let end = section.start + section.rows.len();
+        let (first, last) = self.mounted_in(section.start, end);
+        let rows = section.rows[first - section.start..last - section.start].iter().flatten();
+        let columns = [
+            TableColumn::Date,
+            TableColumn::Time,
+            TableColumn::Site,
+            TableColumn::Title,
+            TableColumn::Tags,
+            TableColumn::Completed,
+        ];
+        let spacer = |rows: usize| {
+            html! {
+                <tbody>
+                    <tr><td colspan="7" style=format!("height: {:.0}px; padding: 0", rows as f64 * self.row_height)/></tr>
+                </tbody>
+            }
+        };
+        html! {
+            <table class="cards-table">
+                <thead>
+                <tr>
+                    <th></th>
                     {
-                        match item.url.as_ref() {
-                            Some(url) => html! {
-                                <a href={ url.to_string() }> { content } </a>
-                            },
-                            None => html! {
-                                { content }
+                        for columns.iter().map(|column| {
+                            let column = *column;
+                            let arrow = match (column == self.sort, self.ascending) {
+                                (true, true) => " ▲",
+                                (true, false) => " ▼",
+                                (false, _) => "",
+                            };
+                            html! {
+                                <th onclick=self.link.callback(move |_| CardsMsg::SortBy(column))>
+                                    { column.label() }{ arrow }
+                                </th>
                             }
-                        }
+                        })
                     }
-                </center>
-            </div>
+                </tr>
+                </thead>
+                { spacer(first - section.start) }
+                <tbody ref=mounted>
+                {
+                    for rows.map(|(index, item)| {
+                        let index = *index;
+                        let click_item = (*item).clone();
+                        html! {
+                            <tr class=self.row_class(item.entry_id)
+                                onclick=self.link.callback(move |m| CardsMsg::CardClick(m, index, click_item.clone()))>
+                                <td>{ self.view_select(item.entry_id, index) }</td>
+                                <td class="cards-row-date">{ &item.date }</td>
+                                <td class="cards-row-date">{ &item.time }</td>
+                                <td class="cards-row-site">{ site_name(item) }</td>
+                                <td class="cards-row-title">{ self.view_title(item) }</td>
+                                <td>{ self.view_tags(item.entry_id) }</td>
+                                <td>{ if self.completed.contains(&item.entry_id) { "✓" } else { "" } }</td>
+                            </tr>
+                        }
+                    })
+                }
+                </tbody>
+                { spacer(end - last) }
+            </table>
         }
     }
 
-    fn view_entries(&self) -> Html {
-        let blank = String::from("");
-        match self.entries {
-            Some(ref entries) => {
-                log::info!("{:#?} results fetched.", entries.len());
-
-                html! {
+    fn view_grouping(&self) -> Html {
+        if self.layout == GalleryLayout::Table {
+            return html! {};
+        }
+        let groups = [GalleryGroup::Ungrouped, GalleryGroup::Day, GalleryGroup::Week, GalleryGroup::Month];
+        html! {
+            <span class="cards-grouping">
+                <select class="tags-order" onchange=self.link.callback(CardsMsg::Group)>
                     {
-                        for entries.iter().map(|item| {
-                            // log::info!("{:#?} : item.", item);
-                            let parsed = Url::parse(item.url.as_ref().unwrap_or(&"".to_owned()));
-                            let thumbnail_file = item.thumbnail_file.as_ref().unwrap_or(&blank);
-                            self.view_card(&parsed, &thumbnail_file, &item)
+                        for groups.iter().enumerate().map(|(i, group)| html! {
+                            <option value=i.to_string() selected=*group == self.group>{ group.label() }</option>
                         })
                     }
+                </select>
+                {
+                    if self.group == GalleryGroup::Ungrouped {
+                        html! {}
+                    } else {
+                        html! {
+                            <input type="date" class="cards-jump" title="jump to a date"
+                                onchange=self.link.callback(CardsMsg::JumpTo)/>
+                        }
+                    }
                 }
+            </span>
+        }
+    }
+
+    fn view_layouts(&self) -> Html {
+        html! {
+            <span class="cards-layouts">
+            {
+                for [GalleryLayout::Grid, GalleryLayout::List, GalleryLayout::Table].iter().map(|layout| {
+                    let layout = *layout;
+                    let class = if layout == self.layout { "batch-button cards-layout-current" } else { "batch-button" };
+                    html! {
+                        <button class=class onclick=self.link.callback(move |_| CardsMsg::Layout(layout))>
+                            { layout.label() }
+                        </button>
+                    }
+                })
+            }
+            </span>
+        }
+    }
+
+    // note cards and the list show tags, the table tags and completion
+    fn fetch_details(&mut self) {
+        let server = host().unwrap();
+        // only the tags of this page, not every tag link there is
+        let ids: Vec<String> = self.entry_ids().iter().map(|id| format!("id={}", id)).collect();
+        if ids.is_empty() {
+            self.entry_tags.clear();
+            self.tags_task = None;
+            return;
+        }
+        let request = Request::get(&format!("http://{}/link/entry/tags?{}", server, ids.join("&")))
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = self.link.callback_once(|response: Response<Json<Result<Vec<EntryTag>, anyhow::Error>>>| {
+            let Json(data) = response.into_body();
+            CardsMsg::ReceiveEntryTags(data)
+        });
+        self.tags_task = FetchService::fetch(request, callback).ok();
+        if self.layout == GalleryLayout::Table {
+            let request = Request::get(&format!("http://{}/all/completed", server))
+                .body(Nothing)
+                .expect("Could not build request.");
+            let callback = self.link.callback_once(|response: Response<Json<Result<Vec<i32>, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                CardsMsg::ReceiveCompleted(data)
+            });
+            self.completed_task = FetchService::fetch(request, callback).ok();
+        }
+        if self.layout == GalleryLayout::Grid {
+            let request = Request::get(&format!("http://{}/all/annotations/counts", server))
+                .body(Nothing)
+                .expect("Could not build request.");
+            let callback =
+                self.link.callback_once(|response: Response<Json<Result<Vec<AnnotationCount>, anyhow::Error>>>| {
+                    let Json(data) = response.into_body();
+                    CardsMsg::ReceiveAnnotationCounts(data)
+                });
+            self.counts_task = FetchService::fetch(request, callback).ok();
+        }
+    }
+
+    // entries in the order they are shown, the table sorts them its own way
+    fn displayed<'a>(&self, entries: &'a [Cache]) -> Vec<&'a Cache> {
+        let mut displayed: Vec<&Cache> = entries.iter().collect();
+        if self.layout == GalleryLayout::Table {
+            displayed.sort_by(|a, b| self.compare(a, b));
+        }
+        displayed
+    }
+
+    // ids by position on screen, what selection indices and ranges refer to
+    fn entry_ids(&self) -> Vec<i32> {
+        self.displayed(self.entries.as_deref().unwrap_or_default())
+            .iter()
+            .map(|entry| entry.entry_id)
+            .collect()
+    }
+
+    fn toggle(&mut self, index: usize) {
+        if let Some(entry_id) = self.entry_ids().get(index) {
+            if !self.selected.remove(entry_id) {
+                self.selected.insert(*entry_id);
             }
-            None => {
-                html! { <div> {"No Content"} </div> }
+        }
+        self.anchor = Some(index);
+        self.selecting = true;
+    }
+
+    fn select_range(&mut self, index: usize) {
+        let anchor = self.anchor.unwrap_or(index);
+        let (start, end) = if anchor <= index { (anchor, index) } else { (index, anchor) };
+        let ids = self.entry_ids();
+        self.selected.extend(ids.iter().skip(start).take(end - start + 1));
+        self.selecting = true;
+    }
+
+    // selected entries in gallery order
+    fn selected_entries(&self) -> Vec<Cache> {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(|entry| self.selected.contains(&entry.entry_id))
+            .cloned()
+            .collect()
+    }
+
+    fn view_toolbar(&self) -> Html {
+        if self.selecting {
+            html! {
+                <BatchBar entries=self.selected_entries()
+                    done_callback=self.link.callback(|_| CardsMsg::BatchDone)
+                    clear_callback=self.link.callback(|_| CardsMsg::ClearSelection)/>
+            }
+        } else {
+            html! {
+                <div class="cards-toolbar">
+                    <button class="batch-button" onclick=self.link.callback(|_| CardsMsg::SelectAll)>
+                        {"Select all"}
+                    </button>
+                    { self.view_layouts() }
+                    { self.view_grouping() }
+                </div>
             }
         }
     }
@@ -150,18 +748,70 @@ impl Component for Cards {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         log::info!("Creating cards component");
-        Self {
+        let storage = StorageService::new(Area::Local).ok();
+        let (layout, group) = match &storage {
+            Some(storage) => {
+                let Json(layout) = storage.restore(GALLERY_LAYOUT_KEY);
+                let Json(group) = storage.restore(GALLERY_GROUP_KEY);
+                (layout.unwrap_or(GalleryLayout::Grid), group.unwrap_or(GalleryGroup::Ungrouped))
+            }
+            None => (GalleryLayout::Grid, GalleryGroup::Ungrouped),
+        };
+        let scroll_link = link.clone();
+        let scroll_listener =
+            Closure::wrap(Box::new(move || scroll_link.send_message(CardsMsg::Scroll)) as Box<dyn FnMut()>);
+        for event in ["scroll", "resize"].iter() {
+            let _ = window().add_event_listener_with_callback(event, scroll_listener.as_ref().unchecked_ref());
+        }
+        let mut cards = Self {
+            click_callback: link.callback(|(m, index, item)| CardsMsg::CardClick(m, index, item)),
+            select_callback: link.callback(|(m, index)| CardsMsg::SelectClick(m, index)),
             link: link,
             entries: props.entries,
-            entry_id_mouseover: None,
-            entry_id_click: None,
+            entry_id_click: props.selected_id,
             card_click_callback: props.card_click_callback,
-        }
+            batch_done_callback: props.batch_done_callback,
+            selected: HashSet::new(),
+            selecting: false,
+            anchor: None,
+            layout,
+            group,
+            collapsed: HashSet::new(),
+            scroll_to: None,
+            storage,
+            sort: TableColumn::Date,
+            ascending: false,
+            entry_tags: HashMap::new(),
+            completed: HashSet::new(),
+            annotation_counts: HashMap::new(),
+            tags_task: None,
+            completed_task: None,
+            counts_task: None,
+            rows: (0, 10),
+            row_height: 0.0,
+            grid_columns: GRID_COLUMNS,
+            container: NodeRef::default(),
+            mounted: NodeRef::default(),
+            scroll_listener,
+        };
+        cards.row_height = cards.initial_row_height();
+        cards.fetch_details();
+        cards
     }
 
     fn change(&mut self, props: Self::Properties) -> bool {
-        self.entries = props.entries;
         self.card_click_callback = props.card_click_callback;
+        self.batch_done_callback = props.batch_done_callback;
+        self.entry_id_click = props.selected_id;
+        // App re-renders for many reasons, the details only follow the entries
+        if self.entries != props.entries {
+            self.entries = props.entries;
+            // entries that left the view (deleted, filtered out) can't stay selected
+            let ids: HashSet<i32> = self.entry_ids().into_iter().collect();
+            self.selected.retain(|entry_id| ids.contains(entry_id));
+            self.anchor = None;
+            self.fetch_details();
+        }
 
         true
     }
@@ -170,11 +820,16 @@ impl Component for Cards {
         use CardsMsg::*;
         log::info!("update");
         match msg {
-            CardMouseOver(_m, entry_id) => {
-                self.entry_id_mouseover = Some(entry_id);
-                true
-            }
-            CardClick(_m, entry_id, item) => {
+            CardClick(m, index, item) => {
+                if m.shift_key() {
+                    self.select_range(index);
+                    return true;
+                }
+                if m.ctrl_key() || m.meta_key() || self.selecting {
+                    self.toggle(index);
+                    return true;
+                }
+                let entry_id = item.entry_id;
                 // TODO
                 if self.entry_id_click.unwrap_or(-1) != entry_id {
                     self.entry_id_click = Some(entry_id);
@@ -185,14 +840,163 @@ impl Component for Cards {
                 }
                 true
             }
+            SelectClick(m, index) => {
+                if m.shift_key() {
+                    self.select_range(index);
+                } else {
+                    self.toggle(index);
+                }
+                true
+            }
+            SelectAll => {
+                self.selected = self.entry_ids().into_iter().collect();
+                self.selecting = true;
+                true
+            }
+            BatchDone => {
+                self.fetch_details();
+                self.batch_done_callback.emit(());
+                false
+            }
+            ClearSelection => {
+                self.selected.clear();
+                self.selecting = false;
+                self.anchor = None;
+                true
+            }
+            Layout(layout) => {
+                self.layout = layout;
+                // positions change with the order
+                self.anchor = None;
+                self.row_height = self.initial_row_height();
+                if let Some(storage) = self.storage.as_mut() {
+                    storage.store(GALLERY_LAYOUT_KEY, Json(&layout));
+                }
+                self.fetch_details();
+                true
+            }
+            SortBy(column) => {
+                if self.sort == column {
+                    self.ascending = !self.ascending;
+                } else {
+                    self.sort = column;
+                    self.ascending = true;
+                }
+                self.anchor = None;
+                true
+            }
+            ReceiveEntryTags(response) => {
+                self.tags_task = None;
+                match response {
+                    Ok(pairs) => {
+                        self.entry_tags.clear();
+                        for pair in pairs {
+                            self.entry_tags.entry(pair.entry_id).or_default().push(pair.tag);
+                        }
+                    }
+                    Err(error) => log::info!("entry tags receive error: {}", error),
+                }
+                true
+            }
+            ReceiveCompleted(response) => {
+                self.completed_task = None;
+                match response {
+                    Ok(ids) => self.completed = ids.into_iter().collect(),
+                    Err(error) => log::info!("completed receive error: {}", error),
+                }
+                true
+            }
+            ReceiveAnnotationCounts(response) => {
+                self.counts_task = None;
+                match response {
+                    Ok(counts) => {
+                        self.annotation_counts = counts.into_iter().map(|count| (count.entry_id, count.count)).collect()
+                    }
+                    Err(error) => log::info!("annotation counts receive error: {}", error),
+                }
+                true
+            }
+            Group(ChangeData::Select(select)) => {
+                let groups = [GalleryGroup::Ungrouped, GalleryGroup::Day, GalleryGroup::Week, GalleryGroup::Month];
+                self.group = select
+                    .value()
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| groups.get(i).copied())
+                    .unwrap_or(GalleryGroup::Ungrouped);
+                self.collapsed.clear();
+                if let Some(storage) = self.storage.as_mut() {
+                    storage.store(GALLERY_GROUP_KEY, Json(&self.group));
+                }
+                true
+            }
+            Group(_) => false,
+            ToggleGroup(key) => {
+                if !self.collapsed.remove(&key) {
+                    self.collapsed.insert(key);
+                }
+                true
+            }
+            JumpTo(ChangeData::Value(date)) => {
+                // the newest group starting on or before the date, else the oldest one
+                let entries = self.entries.as_deref().unwrap_or_default();
+                let groups = group_entries(entries, self.group);
+                let (target, _) = self.group.period(&date);
+                let key = groups
+                    .iter()
+                    .find(|group| group.key <= target)
+                    .or_else(|| groups.last())
+                    .map(|group| group.key.clone());
+                if let Some(key) = key {
+                    self.collapsed.remove(&key);
+                    self.scroll_to = Some(key);
+                }
+                true
+            }
+            JumpTo(_) => false,
+            Scroll => {
+                self.measure_columns();
+                let rows = self.visible_rows();
+                if rows == self.rows {
+                    return false;
+                }
+                self.rows = rows;
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, _first_render: bool) {
+        if let Some(key) = self.scroll_to.take() {
+            if let Some(element) = document().get_element_by_id(&format!("cards-group-{}", key)) {
+                element.scroll_into_view();
+            }
+        }
+        // cards vary in height, spacers use the average of the mounted rows
+        let mounted_rows = self.measured_rows();
+        if let Some(mounted) = self.mounted.cast::<HtmlElement>() {
+            let measured = mounted.offset_height() as f64 / mounted_rows.max(1) as f64;
+            if mounted_rows > 0 && measured > 0.0 && (measured - self.row_height).abs() > 0.1 * self.row_height {
+                self.row_height = measured;
+            }
+        }
+        self.measure_columns();
+        // content above may have moved, or the entries changed
+        self.link.send_message(CardsMsg::Scroll);
+    }
+
+    fn destroy(&mut self) {
+        for event in ["scroll", "resize"].iter() {
+            let _ = window().remove_event_listener_with_callback(event, self.scroll_listener.as_ref().unchecked_ref());
         }
     }
 
     fn view(&self) -> Html {
         html! {
-              <div class="cards">
-                  { self.view_entries() }
-              </div>
+            <div>
+                { self.view_toolbar() }
+                { self.view_entries() }
+            </div>
         }
     }
 }
@@ -0,0 +1,201 @@
+use crate::query::*;
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use yew::format::{Json, Nothing};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::DialogService;
+use yew::utils::host;
+
+// how often the sidebar counts are refreshed
+const COUNT_SECONDS: u64 = 60;
+
+// A named gallery query kept in the local store under its name
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Collection {
+    pub name: String,
+    pub query: GalleryQuery,
+}
+
+#[derive(Debug)]
+pub enum CollectionsMsg {
+    Loaded(Vec<Collection>),
+    Refresh,
+    ReceiveCount(String, Result<usize, anyhow::Error>),
+    Save,
+    Remove(String),
+    Click(GalleryQuery),
+}
+
+pub struct Collections {
+    link: ComponentLink<Self>,
+    props: Props,
+    collections: Vec<Collection>,
+    counts: HashMap<String, usize>,
+    count_tasks: Vec<FetchTask>,
+    _interval: IntervalTask,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    // the query the gallery shows now, what Save stores
+    pub current: GalleryQuery,
+    pub collection_click_callback: Callback<GalleryQuery>,
+}
+
+impl Collections {
+    fn load(&self) {
+        store::get_all_json(store::COLLECTIONS, self.link.callback(CollectionsMsg::Loaded));
+    }
+
+    fn refresh_counts(&mut self) {
+        let server = host().unwrap();
+        self.count_tasks.clear();
+        for collection in self.collections.iter() {
+            let request = Request::get(&collection.query.count_url(&server))
+                .body(Nothing)
+                .expect("Could not build request.");
+            let name = collection.name.clone();
+            let callback = self.link.callback_once(
+                move |response: Response<Json<Result<usize, anyhow::Error>>>| {
+                    let Json(data) = response.into_body();
+                    CollectionsMsg::ReceiveCount(name, data)
+                },
+            );
+            if let Ok(task) = FetchService::fetch(request, callback) {
+                self.count_tasks.push(task);
+            }
+        }
+    }
+
+    fn view_count(&self, name: &str) -> String {
+        match self.counts.get(name) {
+            Some(count) => count.to_string(),
+            None => String::from("..."),
+        }
+    }
+}
+
+impl Component for Collections {
+    type Message = CollectionsMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let interval = IntervalService::spawn(
+            Duration::from_secs(COUNT_SECONDS),
+            link.callback(|_| CollectionsMsg::Refresh),
+        );
+        let collections = Self {
+            link,
+            props,
+            collections: vec![],
+            counts: HashMap::new(),
+            count_tasks: vec![],
+            _interval: interval,
+        };
+        collections.load();
+        collections
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            CollectionsMsg::Loaded(collections) => {
+                self.collections = collections;
+                self.refresh_counts();
+                true
+            }
+            CollectionsMsg::Refresh => {
+                self.refresh_counts();
+                false
+            }
+            CollectionsMsg::ReceiveCount(name, response) => {
+                match response {
+                    Ok(count) => {
+                        self.counts.insert(name, count);
+                    }
+                    Err(error) => log::info!("collection count error for {}: {}", name, error),
+                }
+                true
+            }
+            CollectionsMsg::Save => {
+                let name = DialogService::prompt("Save this view as", None)
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty());
+                if let Some(name) = name {
+                    let exists = self.collections.iter().any(|c| c.name == name);
+                    if exists && !DialogService::confirm(&format!("Replace the collection \"{}\"?", name)) {
+                        return false;
+                    }
+                    let collection = Collection {
+                        name: name.clone(),
+                        query: self.props.current.clone(),
+                    };
+                    store::put_json(store::COLLECTIONS, &name, &collection);
+                    self.collections.retain(|c| c.name != name);
+                    self.collections.push(collection);
+                    self.collections.sort_by(|a, b| a.name.cmp(&b.name));
+                    self.counts.remove(&name);
+                    self.refresh_counts();
+                    return true;
+                }
+                false
+            }
+            CollectionsMsg::Remove(name) => {
+                if !DialogService::confirm(&format!("Remove the collection \"{}\"?", name)) {
+                    return false;
+                }
+                store::delete(store::COLLECTIONS, &name);
+                self.collections.retain(|c| c.name != name);
+                self.counts.remove(&name);
+                true
+            }
+            CollectionsMsg::Click(query) => {
+                self.props.collection_click_callback.emit(query);
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="collections">
+                <div class="collections-title">{"Collections"}</div>
+                {
+                    for self.collections.iter().map(|collection| {
+                        let query = collection.query.clone();
+                        let name = collection.name.clone();
+                        let style = if collection.query == self.props.current {
+                            "collection collection-selected"
+                        } else {
+                            "collection"
+                        };
+                        html! {
+                            <div class=style title=collection.query.to_string()>
+                                <span class="collection-name"
+                                    onclick=self.link.callback(move |_| CollectionsMsg::Click(query.clone()))>
+                                    { &collection.name }
+                                </span>
+                                <span class="collection-count">{ self.view_count(&collection.name) }</span>
+                                <span class="collection-remove" title="remove"
+                                    onclick=self.link.callback(move |_| CollectionsMsg::Remove(name.clone()))>
+                                    {"×"}
+                                </span>
+                            </div>
+                        }
+                    })
+                }
+                <button class="collection-save" onclick=self.link.callback(|_| CollectionsMsg::Save)>
+                    {"+ Save this view"}
+                </button>
+            </div>
+        }
+    }
+}
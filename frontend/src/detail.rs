@@ -1,42 +1,126 @@
-use serde::{Deserialize, Serialize};
-use serde_json::json;
 use crate::api::*;
+use crate::app_router::*;
+use crate::sync::*;
+use yew::agent::{Dispatched, Dispatcher};
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
 use yew::{
     format::{Json, Nothing},
     prelude::*,
     utils::*,
 };
 use yew::Properties;
+use yew_router::agent::{RouteAgentDispatcher, RouteRequest};
+use yew_router::prelude::*;
+use yew_router::route::Route;
 use wasm_bindgen::prelude::*;
 use crate::external::*;
 // use wasm_bindgen::JsCast;
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct CompletedResponse {
-    pub code: i64,
-}
+pub type Link = RouterAnchor<AppRoute>;
 
 pub enum DetailMsg {
+    // fetches the entry when only its id is known, then everything about it
+    Load,
+    ReceiveEntry(Result<Vec<Cache>, anyhow::Error>),
     CompletedChange(ChangeData),
-    CompletedResponse(Result<Vec<CompletedResponse>, anyhow::Error>),
     GetCompleted,
     ReceiveCompleted(Result<Vec<bool>, anyhow::Error>),
+    ReceiveAnnotations(Result<Vec<Annotation>, anyhow::Error>),
+    SaveAnnotation,
+    Delete,
+    ContentEdit(String),
+    SaveContent,
+    ReceiveTags(Result<Vec<String>, anyhow::Error>),
+    ReceiveAllTags(Result<Vec<String>, anyhow::Error>),
+    TagInput(String),
+    AddTag,
+    RemoveTag(String),
+    ReceiveRelated(Result<Vec<RelatedEntry>, anyhow::Error>),
+    OpenRelated(Cache),
+    // moves through the gallery results, -1 for the previous entry
+    Step(isize),
+    KeyDown(KeyboardEvent),
 }
 
 pub struct Detail {
     pub link: ComponentLink<Self>,
     pub entry: Option<Cache>,
+    // the entry asked for, by route or gallery selection
+    requested: Option<i32>,
+    entry_task: Option<FetchTask>,
+    // the server has no entry with the requested id
+    missing: bool,
+    error: Option<String>,
+    delete_callback: Callback<Cache>,
+    edit_callback: Callback<Cache>,
+    open_entry_callback: Callback<Cache>,
+    // the gallery results the entry was opened from
+    entries: Vec<Cache>,
+    // arrow keys while nothing inside the page has focus
+    _key_listener: KeyListenerHandle,
     pub ace_editor: Option<JsValue>,
+    // entry the editor was set up for
+    ace_entry: Option<i32>,
     pub completed: bool,
+    // last annotation seen from the server, used to detect edits on both sides
+    pub annotation: Option<String>,
     submit_task: Option<FetchTask>,
+    annotation_task: Option<FetchTask>,
+    tags_task: Option<FetchTask>,
+    all_tags_task: Option<FetchTask>,
+    related_task: Option<FetchTask>,
+    // note text, or the link for captured pages, as being edited
+    content: String,
+    tags: Vec<String>,
+    tag_input: String,
+    // every tag in use, offered as completions
+    all_tags: Vec<String>,
+    // entries sharing tags, site, capture hour or title words, closest first
+    related: Vec<RelatedEntry>,
+    sync: Dispatcher<SyncAgent>,
     // TODO: get ace callback working
     // pub ace_callback: dyn Fn(JsValue) -> (),
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
+    // the gallery selection, used when it is the entry asked for
     pub entry: Option<Cache>,
+    // set when opened by url, wins over the gallery selection
+    #[prop_or_default]
+    pub entry_id: Option<i32>,
+    // moves the entry to the archive
+    #[prop_or_default]
+    pub delete_callback: Callback<Cache>,
+    // the entry's content or tags were changed
+    #[prop_or_default]
+    pub edit_callback: Callback<Cache>,
+    // another entry was picked to show instead
+    #[prop_or_default]
+    pub open_entry_callback: Callback<Cache>,
+    // current gallery results, for previous and next
+    #[prop_or_default]
+    pub entries: Vec<Cache>,
+}
+
+// what the user edits: the link of a captured page, the text of a note
+fn editable_content(entry: &Option<Cache>) -> String {
+    match entry {
+        Some(entry) => entry.url.clone().or_else(|| entry.content.clone()).unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+// the editor text of an entry without notes yet
+fn default_note(entry: &Option<Cache>) -> String {
+    match entry {
+        Some(entry) => {
+            let title = entry.content.clone().unwrap_or_default();
+            if entry.url.is_none() { title } else { ["# Notes on", &title].join(" ") }
+        }
+        None => "No Entry Selected".to_string(),
+    }
 }
 
 fn youtube_url(url: String) -> String {
@@ -81,32 +165,259 @@ fn completed_checkbox(detail: &Detail) -> Html {
     }
 }
 
+fn edit_fields(detail: &Detail) -> Html {
+    if detail.entry.is_none() {
+        return html! {};
+    }
+    let unchanged = detail.content.trim() == editable_content(&detail.entry) || detail.content.trim().is_empty();
+    html! {
+        <div class="detail-edit">
+            <textarea class="detail-content" rows="2"
+                value=detail.content.clone()
+                oninput=detail.link.callback(|e: InputData| DetailMsg::ContentEdit(e.value))/>
+            <button class="save-note-button shadow-sm bg-white rounded" disabled=unchanged
+                onclick=detail.link.callback(|_| DetailMsg::SaveContent)>
+                {"Save"}
+            </button>
+            <div class="detail-tags">
+                {
+                    for detail.tags.iter().map(|tag| {
+                        let remove = tag.clone();
+                        html! {
+                            <span class="topic-tag-addnote detail-tag">
+                                { tag }
+                                <span class="detail-tag-remove" title="remove tag"
+                                    onclick=detail.link.callback(move |_| DetailMsg::RemoveTag(remove.clone()))>
+                                    {"×"}
+                                </span>
+                            </span>
+                        }
+                    })
+                }
+                // change fires on enter and when a completion is picked
+                <input type="text" class="detail-tag-input" placeholder="add tag" list="detail-tag-options"
+                    value=detail.tag_input.clone()
+                    oninput=detail.link.callback(|e: InputData| DetailMsg::TagInput(e.value))
+                    onchange=detail.link.callback(|_| DetailMsg::AddTag)/>
+                <datalist id="detail-tag-options">
+                    {
+                        for detail.all_tags.iter().filter(|tag| !detail.tags.contains(tag)).map(|tag| {
+                            html! { <option value=tag.clone()/> }
+                        })
+                    }
+                </datalist>
+            </div>
+        </div>
+    }
+}
+
+// keys typed into the editor, the content or a tag are not shortcuts
+fn is_typing(e: &KeyboardEvent) -> bool {
+    e.target()
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        .map(|element| matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"))
+        .unwrap_or(false)
+}
+
+fn step_controls(detail: &Detail) -> Html {
+    let position = match detail.position() {
+        Some(position) => position,
+        None => return html! {},
+    };
+    let total = detail.entries.len();
+    let last = position + 1 >= total;
+    html! {
+        <div class="detail-steps">
+            <button class="detail-step shadow-sm bg-white rounded" disabled=position == 0
+                title="previous (← or k)"
+                onclick=detail.link.callback(|_| DetailMsg::Step(-1))>
+                {"‹ Previous"}
+            </button>
+            <span class="detail-position">{ format!("{} of {}", position + 1, total) }</span>
+            <button class="detail-step shadow-sm bg-white rounded" disabled=last
+                title="next (→ or j)"
+                onclick=detail.link.callback(|_| DetailMsg::Step(1))>
+                {"Next ›"}
+            </button>
+        </div>
+    }
+}
+
+// shown instead of the entry while it loads or when there is none
+fn entry_status(detail: &Detail) -> Html {
+    let message = match (detail.requested, &detail.error) {
+        (None, _) => String::from("No entry selected, pick one in the gallery"),
+        (Some(entry_id), _) if detail.missing => format!("Entry {} was not found", entry_id),
+        (Some(entry_id), Some(error)) => format!("Could not load entry {}: {}", entry_id, error),
+        (Some(entry_id), None) => format!("Loading entry {}...", entry_id),
+    };
+    html! {
+        <div class="detail-status shadow-sm p-3 mb-5 bg-white rounded">
+            <h5>{ message }</h5>
+            <Link route=AppRoute::Gallery>{"Back to the gallery"}</Link>
+        </div>
+    }
+}
+
+// opens another entry under its own url
+fn open_entry(callback: &Callback<Cache>, entry: Cache) {
+    let route = AppRoute::DetailById(entry.entry_id);
+    callback.emit(entry);
+    RouteAgentDispatcher::<()>::new().send(RouteRequest::ChangeRoute(Route::from(route)));
+}
+
+fn related_panel(detail: &Detail) -> Html {
+    if detail.entry.is_none() {
+        return html! {};
+    }
+    let items = if detail.related.is_empty() {
+        let message = if detail.related_task.is_some() { "Loading..." } else { "Nothing related yet" };
+        html! { <font color="grey">{ message }</font> }
+    } else {
+        html! {
+            for detail.related.iter().map(|related| {
+                let entry = related.entry.clone();
+                let title = entry.content.clone().or_else(|| entry.url.clone()).unwrap_or_default();
+                html! {
+                    <div class="related-entry" title=title.clone()
+                        onclick=detail.link.callback(move |_| DetailMsg::OpenRelated(entry.clone()))>
+                        <img class="related-thumbnail" src=related.entry.thumbnail_file.clone().unwrap_or_default()/>
+                        <div class="related-text">
+                            <div class="related-title">{ title }</div>
+                            <div class="related-date">{ &related.entry.date }</div>
+                            { for related.reasons.iter().map(|reason| html! { <span class="related-reason">{ reason }</span> }) }
+                        </div>
+                    </div>
+                }
+            })
+        }
+    };
+    html! {
+        <div class="detail-related shadow p-3 mb-5 bg-body rounded">
+            <h5>{"Related"}</h5>
+            { items }
+        </div>
+    }
+}
+
+impl Detail {
+    // where the entry sits in the gallery results
+    fn position(&self) -> Option<usize> {
+        let entry_id = self.entry.as_ref()?.entry_id;
+        self.entries.iter().position(|e| e.entry_id == entry_id)
+    }
+}
+
 impl Component for Detail {
     type Message = DetailMsg;
     type Properties = Props;
     // let callback = |buffer: JsValue| log::info!("ace callback");
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let cb = link.callback_once(|_: String| DetailMsg::GetCompleted);
-        cb.emit("".to_string()); // TODO - what's the right way to handle a message without parameters
-        log::info!("sent GetCompleted message");
+        let requested = props.entry_id.or_else(|| props.entry.as_ref().map(|e| e.entry_id));
+        let entry = props.entry.filter(|e| Some(e.entry_id) == requested);
+        link.send_message(DetailMsg::Load);
+        let key_listener = KeyboardService::register_key_down(&document(), link.callback(DetailMsg::KeyDown));
         Self {
             link: link,
-            entry: props.entry,
+            content: editable_content(&entry),
+            entry,
+            requested,
+            entry_task: None,
+            missing: false,
+            error: None,
+            delete_callback: props.delete_callback,
+            edit_callback: props.edit_callback,
+            open_entry_callback: props.open_entry_callback,
+            entries: props.entries,
+            _key_listener: key_listener,
             ace_editor: None,
+            ace_entry: None,
             completed: false,
-            submit_task: None
+            annotation: None,
+            submit_task: None,
+            annotation_task: None,
+            tags_task: None,
+            all_tags_task: None,
+            related_task: None,
+            tags: vec![],
+            tag_input: String::new(),
+            all_tags: vec![],
+            related: vec![],
+            sync: SyncAgent::dispatcher(),
             // ace_callback: unimplemented!(),
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> bool {
         log::info!("updated entry to {:?}", props.entry);
-        self.entry = props.entry;
+        let requested = props.entry_id.or_else(|| props.entry.as_ref().map(|e| e.entry_id));
+        let switched = requested != self.requested;
+        // the gallery selection, or what was already fetched for this id
+        self.entry = props
+            .entry
+            .filter(|e| Some(e.entry_id) == requested)
+            .or_else(|| self.entry.take().filter(|e| Some(e.entry_id) == requested));
+        self.requested = requested;
+        self.delete_callback = props.delete_callback;
+        self.edit_callback = props.edit_callback;
+        self.open_entry_callback = props.open_entry_callback;
+        self.entries = props.entries;
+        if switched {
+            // another entry, start over with its state
+            self.content = editable_content(&self.entry);
+            self.completed = false;
+            self.annotation = None;
+            self.tags = vec![];
+            self.related = vec![];
+            self.missing = false;
+            self.error = None;
+            // drops the request for the previous entry
+            self.entry_task = None;
+            self.link.send_message(DetailMsg::Load);
+        }
         true
     }
 
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
+            DetailMsg::Load => {
+                match (&self.entry, self.requested) {
+                    (Some(_), _) => self.link.send_message(DetailMsg::GetCompleted),
+                    (None, Some(entry_id)) => {
+                        let query = format!("http://{}/get/entry/{}", host().unwrap(), entry_id);
+                        let request = Request::get(&query)
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                DetailMsg::ReceiveEntry(data)
+                            },
+                        );
+                        self.entry_task = FetchService::fetch(request, callback).ok();
+                    }
+                    (None, None) => (),
+                }
+                false
+            }
+            DetailMsg::ReceiveEntry(entry) => {
+                self.entry_task = None;
+                match entry {
+                    Ok(result) => match result.into_iter().next() {
+                        Some(e) => {
+                            self.content = editable_content(&Some(e.clone()));
+                            self.entry = Some(e);
+                            self.link.send_message(DetailMsg::GetCompleted);
+                        }
+                        None => self.missing = true,
+                    },
+                    Err(error) => {
+                        log::info!("entry receive error: {}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+                true
+            }
             DetailMsg::CompletedChange(v) => {
                 log::info!("completed changed: {:?}", v);
                 match v {
@@ -122,29 +433,11 @@ impl Component for Detail {
                                 }
                                 Some(e) => {
                                     log::info!("completed checbox : entry_id: {:?}", e.entry_id);
-                                    let server = host().unwrap();
-                                    let query = format!("http://{}/submit/completed", server);
                                     let payload = CompletedPayload {
                                         entry_id: e.entry_id,
                                         state: self.completed,
                                     };
-                                    // TODO can we use payload to deserialize here instead?
-                                    // currently payload value isn't used
-                                    let body = json!({"pcEntryID": payload.entry_id,
-                                                      "pcState": payload.state});
-                                    let request = Request::post(query)
-                                        .header("Content-Type", "application/json")
-                                        .body(Json(&body))
-                                        .expect("Could not build request.");
-                                    let callback = self.link.callback_once(
-                                        |response: Response<Json<Result<Vec<CompletedResponse>, anyhow::Error>>>| {
-                                            let Json(data) = response.into_body();
-                                            DetailMsg::CompletedResponse(data)
-                                        },
-                                    );
-                                    let task = FetchService::fetch(request, callback).expect("failed to start request");
-                                    self.submit_task = Some(task);
-                                    // e.entry_id ;
+                                    self.sync.send(SyncRequest::Enqueue(OutboxOp::Completed(payload)));
                                     false
                                 }
                             }
@@ -155,10 +448,6 @@ impl Component for Detail {
                     }
                 }
             }
-            DetailMsg::CompletedResponse(d) => {
-                log::info!("completed response {:?}", d);
-                false
-            }
             DetailMsg::GetCompleted => {
                 match &self.entry { 
                     None => { log::info!("no entry value"); }
@@ -177,6 +466,51 @@ impl Component for Detail {
                         );
                         let task = FetchService::fetch(request, callback).expect("failed to start request");
                         self.submit_task = Some(task);
+                        let query = format!("http://{}/get/annotations/{}", server, e.entry_id);
+                        let request = Request::get(&query)
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<Annotation>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                DetailMsg::ReceiveAnnotations(data)
+                            },
+                        );
+                        let task = FetchService::fetch(request, callback).expect("failed to start request");
+                        self.annotation_task = Some(task);
+                        let query = format!("http://{}/get/tags/{}", server, e.entry_id);
+                        let request = Request::get(&query)
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<String>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                DetailMsg::ReceiveTags(data)
+                            },
+                        );
+                        self.tags_task = FetchService::fetch(request, callback).ok();
+                        let query = format!("http://{}/all/tags", server);
+                        let request = Request::get(&query)
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<String>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                DetailMsg::ReceiveAllTags(data)
+                            },
+                        );
+                        self.all_tags_task = FetchService::fetch(request, callback).ok();
+                        let query = format!("http://{}/related/{}", server, e.entry_id);
+                        let request = Request::get(&query)
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<RelatedEntry>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                DetailMsg::ReceiveRelated(data)
+                            },
+                        );
+                        self.related_task = FetchService::fetch(request, callback).ok();
                     }
                 }
                 false
@@ -196,54 +530,229 @@ impl Component for Detail {
                 }
                 true
             }
+            DetailMsg::ReceiveAnnotations(annotations) => {
+                match annotations {
+                    Ok(result) => {
+                        self.annotation = result.last().map(|a| a.content.clone());
+                        if let Some(editor) = &self.ace_editor {
+                            let content = self.annotation.clone().unwrap_or_else(|| default_note(&self.entry));
+                            ace_set_value(editor, &content);
+                        }
+                    }
+                    Err(error) => {
+                        log::info!("annotation receive error, error is:");
+                        log::info!("{}", &error.to_string());
+                    }
+                }
+                false
+            }
+            DetailMsg::SaveAnnotation => {
+                if let (Some(editor), Some(e)) = (&self.ace_editor, &self.entry) {
+                    let payload = AnnotationPayload {
+                        entry_id: e.entry_id,
+                        content: ace_get_value(editor),
+                    };
+                    self.sync.send(SyncRequest::Enqueue(OutboxOp::Annotation {
+                        base: self.annotation.clone(),
+                        payload: payload.clone(),
+                    }));
+                    // later saves build on this one
+                    self.annotation = Some(payload.content);
+                }
+                false
+            }
+            DetailMsg::Delete => {
+                if let Some(e) = &self.entry {
+                    self.delete_callback.emit(e.clone());
+                }
+                false
+            }
+            DetailMsg::ContentEdit(content) => {
+                self.content = content;
+                true
+            }
+            DetailMsg::SaveContent => {
+                let content = self.content.trim().to_string();
+                if content.is_empty() || content == editable_content(&self.entry) {
+                    return false;
+                }
+                if let Some(e) = self.entry.as_mut() {
+                    self.sync.send(SyncRequest::Enqueue(OutboxOp::Edit(EditPayload {
+                        entry_id: e.entry_id,
+                        content: content.clone(),
+                    })));
+                    if e.url.is_some() {
+                        e.url = Some(content.clone());
+                    } else {
+                        e.content = Some(content.clone());
+                    }
+                    self.content = content;
+                    self.edit_callback.emit(e.clone());
+                }
+                true
+            }
+            DetailMsg::ReceiveTags(tags) => {
+                match tags {
+                    Ok(result) => self.tags = result,
+                    Err(error) => log::info!("tags receive error: {}", error),
+                }
+                self.tags_task = None;
+                true
+            }
+            DetailMsg::ReceiveAllTags(tags) => {
+                match tags {
+                    Ok(result) => self.all_tags = result,
+                    Err(error) => log::info!("all tags receive error: {}", error),
+                }
+                self.all_tags_task = None;
+                true
+            }
+            DetailMsg::TagInput(value) => {
+                self.tag_input = value;
+                false
+            }
+            DetailMsg::AddTag => {
+                let tag = self.tag_input.trim().to_string();
+                self.tag_input = String::new();
+                match &self.entry {
+                    Some(e) if !tag.is_empty() && !self.tags.contains(&tag) => {
+                        self.sync.send(SyncRequest::Enqueue(OutboxOp::Tags(TagsPayload {
+                            entry_id: e.entry_id,
+                            tags: vec![tag.clone()],
+                        })));
+                        self.tags.push(tag.clone());
+                        if !self.all_tags.contains(&tag) {
+                            self.all_tags.push(tag);
+                            self.all_tags.sort();
+                        }
+                        self.edit_callback.emit(e.clone());
+                    }
+                    _ => (),
+                }
+                true
+            }
+            DetailMsg::RemoveTag(tag) => {
+                if let Some(e) = &self.entry {
+                    self.sync.send(SyncRequest::Enqueue(OutboxOp::Untag(TagsPayload {
+                        entry_id: e.entry_id,
+                        tags: vec![tag.clone()],
+                    })));
+                    self.tags.retain(|t| *t != tag);
+                    self.edit_callback.emit(e.clone());
+                }
+                true
+            }
+            DetailMsg::ReceiveRelated(related) => {
+                match related {
+                    Ok(result) => self.related = result,
+                    Err(error) => log::info!("related receive error: {}", error),
+                }
+                self.related_task = None;
+                true
+            }
+            DetailMsg::OpenRelated(entry) => {
+                open_entry(&self.open_entry_callback, entry);
+                false
+            }
+            DetailMsg::Step(offset) => {
+                let target = self
+                    .position()
+                    .and_then(|position| {
+                        if offset < 0 {
+                            position.checked_sub(offset.unsigned_abs())
+                        } else {
+                            position.checked_add(offset as usize)
+                        }
+                    })
+                    .and_then(|index| self.entries.get(index));
+                if let Some(entry) = target {
+                    open_entry(&self.open_entry_callback, entry.clone());
+                }
+                false
+            }
+            DetailMsg::KeyDown(e) => {
+                if is_typing(&e) || e.ctrl_key() || e.meta_key() || e.alt_key() {
+                    return false;
+                }
+                let offset = match e.key().as_str() {
+                    "ArrowLeft" | "k" => -1,
+                    "ArrowRight" | "j" => 1,
+                    _ => return false,
+                };
+                e.prevent_default();
+                self.link.send_message(DetailMsg::Step(offset));
+                false
+            }
         }
     }
 
     
-    fn rendered(&mut self, first_render: bool) {
-        log::info!("calling init_ace");
-        // self.ace_editor = Some(init_ace());
-        init_ace();
-        log::info!("called init_ace");
+    fn rendered(&mut self, _first_render: bool) {
+        // there is no editor until the entry is there, then one per entry
+        let entry_id = self.entry.as_ref().map(|e| e.entry_id);
+        if entry_id.is_none() {
+            self.ace_editor = None;
+        } else if entry_id != self.ace_entry {
+            log::info!("calling init_ace");
+            self.ace_editor = Some(init_ace());
+            log::info!("called init_ace");
+        }
+        self.ace_entry = entry_id;
     }
 
     fn view(&self) -> Html {
-        let default = String::from("https://unsplash.it/600/800?random");
         log::info!("Screen {:?}", &self.entry);
-        let src = match &self.entry {
-            Some(entry) => entry.url.as_ref().unwrap_or(&default),
-            None => &default,
+        let entry = match &self.entry {
+            Some(entry) => entry,
+            None => return entry_status(self),
         };
-        let (src_mapped, iframe_style) = iframeify_url(src.to_string());
-        let title: String = match &self.entry {
-            Some(entry) => entry.content.clone().unwrap_or("".to_string()),
-            None => "".to_string(),
+        // captured pages are shown in a frame, notes as their text
+        let page = match &entry.url {
+            Some(url) => {
+                let (src_mapped, iframe_style) = iframeify_url(url.to_string());
+                log::info!("Screen {:?}", url);
+                html! {
+                    <iframe class="responsive-iframe shadow p-3 mb-5 bg-body rounded" 
+                            sandbox="allow-same-origin allow-scripts allow-popups allow-forms"
+                            src=src_mapped style=iframe_style/>
+                }
+            }
+            None => html! {
+                <div class="detail-note">{ entry.content.clone().unwrap_or_default() }</div>
+            },
         };
         // TODO note_content branch on is url?
-        let note_content = match &self.entry {
-            Some(entry) => if entry.url.is_none() { title } 
-                           else { ["# Notes on", &title].join(" ") },
-            None => "No Entry Selected".to_string(),
-        };
-        log::info!("Screen {:?}", src);
+        let note_content = default_note(&self.entry);
         html! {
-            <div>
+            <div onkeydown=self.link.callback(DetailMsg::KeyDown)>
+                { step_controls(self) }
+                <div class="detail-with-related">
                 <div class="twocol-equal">
                     <div class="container shadow p-3 mb-5 bg-body rounded">
-                        <iframe class="responsive-iframe shadow p-3 mb-5 bg-body rounded" 
-                                sandbox="allow-same-origin allow-scripts allow-popups allow-forms"
-                                src=src_mapped style=iframe_style/>
+                        { page }
                     </div>
                     <div style="height:85vh" class="shadow p-3 mb-5 bg-body rounded">
-                        <div id="editor" style="height:90%;">
+                        { edit_fields(self) }
+                        <div id="editor" style="height:65%;">
                             { note_content } 
                         </div>
                         <p/>
                         <center>
                         { completed_checkbox(self) }
+                        <button class="save-note-button shadow-sm bg-white rounded"
+                            onclick=self.link.callback(|_| DetailMsg::SaveAnnotation)>
+                            {"Save Notes"}
+                        </button>
+                        <button class="save-note-button delete-entry-button shadow-sm bg-white rounded"
+                            disabled=self.entry.is_none()
+                            onclick=self.link.callback(|_| DetailMsg::Delete)>
+                            {"Delete"}
+                        </button>
                         </center>
                     </div>
                 </div>
+                { related_panel(self) }
+                </div>
             </div>
         }
 
@@ -16,4 +16,10 @@ extern "C" {
 
     #[wasm_bindgen]
     pub fn ace_add_callback(editor: JsValue, callback: &dyn Fn(JsValue) -> ()) -> JsValue;
+
+    #[wasm_bindgen]
+    pub fn ace_get_value(editor: &JsValue) -> String;
+
+    #[wasm_bindgen]
+    pub fn ace_set_value(editor: &JsValue, value: &str);
 }
@@ -0,0 +1,324 @@
+use crate::api::*;
+use crate::app_router::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use yew::format::{Json, Nothing};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::utils::host;
+use yew_router::agent::{RouteAgentDispatcher, RouteRequest};
+use yew_router::route::Route;
+
+// Tag co-occurrence graph: tags sized by how many entries use them, linked
+// by how many entries they share, laid out with a force simulation.
+
+const WIDTH: f64 = 1000.0;
+const HEIGHT: f64 = 700.0;
+// simulation steps per animation frame
+const STEPS_PER_TICK: usize = 3;
+const TICK_MS: u64 = 30;
+// the layout stops once nodes move less than this per step
+const MIN_TEMPERATURE: f64 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub tag: String,
+    pub count: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub a: usize,
+    pub b: usize,
+    // entries carrying both tags
+    pub weight: usize,
+}
+
+// The most used tags and the number of entries each pair of them shares.
+fn build_graph(pairs: &[EntryTag], max_nodes: usize) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+    let mut by_entry: HashMap<i32, Vec<&str>> = HashMap::new();
+    for pair in pairs {
+        let tags = by_entry.entry(pair.entry_id).or_default();
+        if !tags.contains(&pair.tag.as_str()) {
+            tags.push(&pair.tag);
+        }
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tags in by_entry.values() {
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked.truncate(max_nodes);
+
+    let index: HashMap<&str, usize> = ranked.iter().enumerate().map(|(i, (tag, _))| (*tag, i)).collect();
+    // start on a spiral so the simulation is the same on every load
+    let nodes = ranked
+        .iter()
+        .enumerate()
+        .map(|(i, (tag, count))| {
+            let radius = 15.0 * (i as f64 + 1.0).sqrt();
+            let angle = i as f64 * 2.399_963;
+            GraphNode {
+                tag: tag.to_string(),
+                count: *count,
+                x: WIDTH / 2.0 + radius * angle.cos(),
+                y: HEIGHT / 2.0 + radius * angle.sin(),
+            }
+        })
+        .collect();
+
+    let mut weights: HashMap<(usize, usize), usize> = HashMap::new();
+    for tags in by_entry.values() {
+        let mut kept: Vec<usize> = tags.iter().filter_map(|tag| index.get(tag).copied()).collect();
+        kept.sort_unstable();
+        for (i, a) in kept.iter().enumerate() {
+            for b in kept[i + 1..].iter() {
+                *weights.entry((*a, *b)).or_default() += 1;
+            }
+        }
+    }
+    let edges = weights.into_iter().map(|((a, b), weight)| GraphEdge { a, b, weight }).collect();
+    (nodes, edges)
+}
+
+// One Fruchterman-Reingold step: all nodes repel, shared entries pull tags
+// together, a weak pull to the centre keeps unconnected tags on screen.
+fn layout_step(nodes: &mut [GraphNode], edges: &[GraphEdge], temperature: f64) {
+    let n = nodes.len();
+    if n == 0 {
+        return;
+    }
+    let k = (WIDTH * HEIGHT / n as f64).sqrt() * 0.5;
+    let mut disp = vec![(0.0, 0.0); n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let dx = nodes[i].x - nodes[j].x;
+            let dy = nodes[i].y - nodes[j].y;
+            let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+            let force = k * k / dist;
+            disp[i].0 += dx / dist * force;
+            disp[i].1 += dy / dist * force;
+            disp[j].0 -= dx / dist * force;
+            disp[j].1 -= dy / dist * force;
+        }
+    }
+    for edge in edges {
+        let dx = nodes[edge.a].x - nodes[edge.b].x;
+        let dy = nodes[edge.a].y - nodes[edge.b].y;
+        let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+        let force = dist * dist / k * (1.0 + (edge.weight as f64).ln());
+        disp[edge.a].0 -= dx / dist * force;
+        disp[edge.a].1 -= dy / dist * force;
+        disp[edge.b].0 += dx / dist * force;
+        disp[edge.b].1 += dy / dist * force;
+    }
+    let gravity = 0.005 * k;
+    for (node, (dx, dy)) in nodes.iter_mut().zip(disp) {
+        let dx = dx - (node.x - WIDTH / 2.0) * gravity;
+        let dy = dy - (node.y - HEIGHT / 2.0) * gravity;
+        let len = (dx * dx + dy * dy).sqrt().max(0.01);
+        let step = len.min(temperature);
+        node.x = (node.x + dx / len * step).clamp(20.0, WIDTH - 20.0);
+        node.y = (node.y + dy / len * step).clamp(20.0, HEIGHT - 20.0);
+    }
+}
+
+#[derive(Debug)]
+pub enum GraphMsg {
+    ReceivePairs(Result<Vec<EntryTag>, anyhow::Error>),
+    Tick,
+    Size(ChangeData),
+    Hover(Option<usize>),
+    NodeClick(String),
+}
+
+pub struct TagGraph {
+    link: ComponentLink<Self>,
+    props: Props,
+    pairs: Vec<EntryTag>,
+    fetch_task: Option<FetchTask>,
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    max_nodes: usize,
+    temperature: f64,
+    // running while the layout settles
+    interval: Option<IntervalTask>,
+    hovered: Option<usize>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    // a tag was picked to filter the gallery on
+    pub tag_click_callback: Callback<String>,
+}
+
+impl TagGraph {
+    fn start_layout(&mut self) {
+        let (nodes, edges) = build_graph(&self.pairs, self.max_nodes);
+        self.nodes = nodes;
+        self.edges = edges;
+        self.temperature = WIDTH / 10.0;
+        self.hovered = None;
+        self.interval = Some(IntervalService::spawn(
+            Duration::from_millis(TICK_MS),
+            self.link.callback(|_| GraphMsg::Tick),
+        ));
+    }
+
+    fn view_edges(&self) -> Html {
+        let max_weight = self.edges.iter().map(|e| e.weight).max().unwrap_or(1) as f64;
+        html! {
+            for self.edges.iter().map(|edge| {
+                let (a, b) = (&self.nodes[edge.a], &self.nodes[edge.b]);
+                let touches = self.hovered.is_some_and(|h| h == edge.a || h == edge.b);
+                let class = if touches { "graph-edge graph-edge-hover" } else { "graph-edge" };
+                html! {
+                    <line class=class x1=format!("{:.1}", a.x) y1=format!("{:.1}", a.y)
+                        x2=format!("{:.1}", b.x) y2=format!("{:.1}", b.y)
+                        stroke-width=format!("{:.2}", 0.5 + 3.0 * edge.weight as f64 / max_weight)>
+                        <title>{ format!("{} and {}: {} entries", a.tag, b.tag, edge.weight) }</title>
+                    </line>
+                }
+            })
+        }
+    }
+
+    fn view_nodes(&self) -> Html {
+        let max_count = self.nodes.iter().map(|n| n.count).max().unwrap_or(1) as f64;
+        html! {
+            for self.nodes.iter().enumerate().map(|(i, node)| {
+                let radius = 4.0 + 16.0 * (node.count as f64 / max_count).sqrt();
+                let class = if self.hovered == Some(i) { "graph-node graph-node-hover" } else { "graph-node" };
+                let tag = node.tag.clone();
+                html! {
+                    <g class=class
+                        onmouseover=self.link.callback(move |_| GraphMsg::Hover(Some(i)))
+                        onmouseout=self.link.callback(|_| GraphMsg::Hover(None))
+                        onclick=self.link.callback(move |_| GraphMsg::NodeClick(tag.clone()))>
+                        <circle cx=format!("{:.1}", node.x) cy=format!("{:.1}", node.y) r=format!("{:.1}", radius)>
+                            <title>{ format!("{}: {} entries", node.tag, node.count) }</title>
+                        </circle>
+                        <text x=format!("{:.1}", node.x + radius + 2.0) y=format!("{:.1}", node.y + 4.0)>
+                            { &node.tag }
+                        </text>
+                    </g>
+                }
+            })
+        }
+    }
+}
+
+impl Component for TagGraph {
+    type Message = GraphMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let request = Request::get(&format!("http://{}/link/entry/tags", host().unwrap()))
+            .body(Nothing)
+            .expect("Could not build request.");
+        let callback = link.callback_once(|response: Response<Json<Result<Vec<EntryTag>, anyhow::Error>>>| {
+            let Json(data) = response.into_body();
+            GraphMsg::ReceivePairs(data)
+        });
+        let fetch_task = FetchService::fetch(request, callback).ok();
+        Self {
+            link,
+            props,
+            pairs: vec![],
+            fetch_task,
+            nodes: vec![],
+            edges: vec![],
+            max_nodes: 60,
+            temperature: 0.0,
+            interval: None,
+            hovered: None,
+            error: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            GraphMsg::ReceivePairs(response) => {
+                self.fetch_task = None;
+                match response {
+                    Ok(pairs) => {
+                        self.pairs = pairs;
+                        self.start_layout();
+                    }
+                    Err(error) => {
+                        log::info!("entry tags receive error: {}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+                true
+            }
+            GraphMsg::Tick => {
+                for _ in 0..STEPS_PER_TICK {
+                    layout_step(&mut self.nodes, &self.edges, self.temperature);
+                    self.temperature *= 0.97;
+                }
+                if self.temperature < MIN_TEMPERATURE {
+                    self.interval = None;
+                }
+                true
+            }
+            GraphMsg::Size(ChangeData::Select(select)) => {
+                self.max_nodes = select.value().parse().unwrap_or(60);
+                self.start_layout();
+                true
+            }
+            GraphMsg::Size(_) => false,
+            GraphMsg::Hover(hovered) => {
+                self.hovered = hovered;
+                true
+            }
+            GraphMsg::NodeClick(tag) => {
+                self.props.tag_click_callback.emit(tag);
+                RouteAgentDispatcher::<()>::new().send(RouteRequest::ChangeRoute(Route::from(AppRoute::Gallery)));
+                false
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let status = match (&self.error, &self.fetch_task) {
+            (Some(error), _) => html! { <div class="search-error">{ error }</div> },
+            (None, Some(_)) => html! { <div>{"Loading..."}</div> },
+            (None, None) if self.nodes.is_empty() => html! { <div>{"No tagged entries yet"}</div> },
+            (None, None) => html! {},
+        };
+        html! {
+            <div class="tag-graph">
+                <div class="tag-graph-controls">
+                    <select class="tags-order" onchange=self.link.callback(GraphMsg::Size)>
+                        {
+                            for [30, 60, 120].iter().map(|size| html! {
+                                <option value=size.to_string() selected=*size == self.max_nodes>
+                                    { format!("{} most used tags", size) }
+                                </option>
+                            })
+                        }
+                    </select>
+                    <font color="grey">{" click a tag to show its entries"}</font>
+                </div>
+                { status }
+                <svg class="tag-graph-svg" viewBox=format!("0 0 {} {}", WIDTH, HEIGHT)>
+                    { self.view_edges() }
+                    { self.view_nodes() }
+                </svg>
+            </div>
+        }
+    }
+}
@@ -5,12 +5,26 @@ mod add_note;
 mod api;
 mod app;
 mod app_router;
+mod archived;
+mod batch;
+mod canonical;
+mod capture;
+mod card;
 mod cards;
+mod collections;
 mod detail;
 mod external;
+mod graph;
+mod popup;
+mod query;
 mod queue;
+mod search_box;
+mod search_results;
 mod settings;
 mod space;
+mod store;
+mod sync;
+mod tag_admin;
 mod tags;
 mod timeline;
 
@@ -19,5 +33,15 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen(start)]
 pub fn run_app() {
     wasm_logger::init(wasm_logger::Config::default());
+    // the browser extension mounts its own popup through run_popup
+    let protocol = yew::utils::window().location().protocol().unwrap_or_default();
+    if protocol.ends_with("-extension:") {
+        return;
+    }
     yew::start_app::<app::App>();
 }
+
+#[wasm_bindgen]
+pub fn run_popup(server: String, url: String, title: String) {
+    yew::start_app_with_props::<popup::Popup>(popup::Props { server, url, title });
+}
@@ -0,0 +1,319 @@
+use crate::api::*;
+use crate::canonical::*;
+use serde_json::json;
+use urlencoding::encode;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::{
+    format::{Json, Nothing, Text},
+    prelude::*,
+};
+
+// Browser extension popup, mounted by `run_popup` instead of the full app.
+// The extension page isn't served by the memex server so the server address
+// comes in as a prop rather than from `host()`.
+
+#[derive(Debug)]
+pub enum PopupMsg {
+    ReceiveSearch(Result<Vec<Cache>, anyhow::Error>),
+    ReceiveTags(Result<Vec<String>, anyhow::Error>),
+    ReceiveCompleted(Result<Vec<bool>, anyhow::Error>),
+    TagEdit(String),
+    TagKeyDown(KeyboardEvent),
+    RemoveTag(String),
+    Submit,
+    Submitted(bool),
+    ToggleCompleted,
+    CompletedSent(bool),
+    // the request with this id is back, its task can go
+    Finished(u32, Box<PopupMsg>),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub server: String,
+    pub url: String,
+    pub title: String,
+}
+
+pub struct Popup {
+    link: ComponentLink<Self>,
+    props: Props,
+    // None while the search is still running
+    existing: Option<Option<Cache>>,
+    completed: Option<bool>,
+    tag: String,
+    tags: Vec<String>,
+    exist_tags: Vec<String>,
+    status: Option<String>,
+    // a capture is in flight, or went through and shouldn't be posted again
+    submitting: bool,
+    submitted: bool,
+    tasks: Vec<(u32, FetchTask)>,
+    task_seq: u32,
+}
+
+impl Popup {
+    fn get<T: 'static>(&mut self, query: String, msg: fn(Result<T, anyhow::Error>) -> PopupMsg)
+    where
+        Json<Result<T, anyhow::Error>>: From<Text>,
+    {
+        log::info!("popup request: {}", query);
+        let request = Request::get(&query)
+            .body(Nothing)
+            .expect("Could not build request.");
+        let id = self.next_task();
+        let callback = self.link.callback_once(
+            move |response: Response<Json<Result<T, anyhow::Error>>>| {
+                let Json(data) = response.into_body();
+                PopupMsg::Finished(id, Box::new(msg(data)))
+            },
+        );
+        match FetchService::fetch(request, callback) {
+            Ok(task) => self.tasks.push((id, task)),
+            Err(error) => self.status = Some(error.to_string()),
+        }
+    }
+
+    fn next_task(&mut self) -> u32 {
+        self.task_seq += 1;
+        self.task_seq
+    }
+
+    fn post(&mut self, query: String, body: serde_json::Value, msg: fn(bool) -> PopupMsg) {
+        let request = Request::post(query)
+            .header("Content-Type", "application/json")
+            .body(Json(&body))
+            .expect("Could not build request.");
+        let id = self.next_task();
+        let callback = self.link.callback_once(move |response: Response<Text>| {
+            PopupMsg::Finished(id, Box::new(msg(response.status().is_success())))
+        });
+        match FetchService::fetch(request, callback) {
+            Ok(task) => self.tasks.push((id, task)),
+            Err(error) => self.status = Some(error.to_string()),
+        }
+    }
+
+    fn add_tag(&mut self) {
+        let tag = self.tag.trim().trim_end_matches(',').to_string();
+        if !tag.is_empty() && !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self.tag = String::from("");
+    }
+
+    fn view_existing(&self, entry: &Cache) -> Html {
+        html! {
+            <div>
+                <div class="popup-status">{ format!("Already in your memex since {}", entry.date) }</div>
+                <label>
+                    <input type="checkbox" checked=self.completed.unwrap_or(false)
+                        disabled=self.completed.is_none()
+                        onchange=self.link.callback(|_| PopupMsg::ToggleCompleted)/>
+                    {" Completed"}
+                </label>
+            </div>
+        }
+    }
+
+    fn view_capture(&self) -> Html {
+        html! {
+            <div>
+                <input type="text" class="popup-tag-input" list="popup-tags"
+                    placeholder="tags (enter or comma to add)"
+                    value=self.tag.clone()
+                    oninput=self.link.callback(|e: InputData| PopupMsg::TagEdit(e.value))
+                    onkeydown=self.link.callback(PopupMsg::TagKeyDown)
+                />
+                <datalist id="popup-tags">
+                    { for self.exist_tags.iter().map(|tag| html! { <option value=tag.clone()/> }) }
+                </datalist>
+                <div class="tags-list-div">
+                {
+                    for self.tags.iter().map(|curr_tag| {
+                        let tag_name = curr_tag.clone();
+                        html! {
+                            <div class="topic-tag-addnote" title="remove"
+                                onclick=self.link.callback(move |_| PopupMsg::RemoveTag(tag_name.clone()))>
+                                { curr_tag }
+                            </div>
+                        }
+                    })
+                }
+                </div>
+                <button class="popup-submit" disabled=self.submitting || self.submitted
+                    onclick=self.link.callback(|_| PopupMsg::Submit)>
+                    { if self.submitted { "Captured" } else { "Capture" } }
+                </button>
+            </div>
+        }
+    }
+}
+
+impl Component for Popup {
+    type Message = PopupMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut popup = Self {
+            link,
+            props,
+            existing: None,
+            completed: None,
+            tag: String::from(""),
+            tags: vec![],
+            exist_tags: vec![],
+            status: None,
+            submitting: false,
+            submitted: false,
+            tasks: vec![],
+            task_seq: 0,
+        };
+        let server = popup.props.server.clone();
+        let key = canonical_url(&popup.props.url).unwrap_or_else(|| popup.props.url.clone());
+        let search = format!("http://{}/duplicates?url={}", server, encode(search_key(&key)));
+        popup.get(search, PopupMsg::ReceiveSearch);
+        popup.get(format!("http://{}/all/tags", server), PopupMsg::ReceiveTags);
+        popup
+    }
+
+    fn change(&mut self, props: Self::Properties) -> bool {
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> bool {
+        let server = self.props.server.clone();
+        match msg {
+            PopupMsg::Finished(id, msg) => {
+                self.tasks.retain(|(task, _)| *task != id);
+                self.update(*msg)
+            }
+            PopupMsg::ReceiveSearch(response) => {
+                match response {
+                    Ok(result) => {
+                        let url = canonical_url(&self.props.url);
+                        let existing = result.into_iter().find(|entry| {
+                            url.is_some() && entry.url.as_deref().and_then(canonical_url) == url
+                        });
+                        if let Some(entry) = &existing {
+                            let query = format!("http://{}/get/completed/{}", server, entry.entry_id);
+                            self.get(query, PopupMsg::ReceiveCompleted);
+                        }
+                        self.existing = Some(existing);
+                    }
+                    Err(error) => {
+                        log::info!("popup search error: {}", error);
+                        self.existing = Some(None);
+                        self.status = Some(format!("Can't reach the memex at {}", server));
+                    }
+                }
+                true
+            }
+            PopupMsg::ReceiveTags(response) => {
+                if let Ok(result) = response {
+                    self.exist_tags = result;
+                }
+                true
+            }
+            PopupMsg::ReceiveCompleted(response) => {
+                if let Ok(result) = response {
+                    self.completed = result.first().copied();
+                }
+                true
+            }
+            PopupMsg::TagEdit(content) => {
+                self.tag = content;
+                if self.tag.ends_with(',') {
+                    self.add_tag();
+                    return true;
+                }
+                false
+            }
+            PopupMsg::TagKeyDown(keypress) => {
+                if keypress.key() == "Enter" {
+                    if self.tag.trim().is_empty() {
+                        self.link.send_message(PopupMsg::Submit);
+                    } else {
+                        self.add_tag();
+                    }
+                    return true;
+                }
+                false
+            }
+            PopupMsg::RemoveTag(tag_name) => {
+                self.tags.retain(|t| *t != tag_name);
+                true
+            }
+            PopupMsg::Submit => {
+                if self.submitting || self.submitted {
+                    return false;
+                }
+                self.submitting = true;
+                self.add_tag();
+                let query = format!("http://{}/submit/note", server);
+                let body = json!({"pnContent": self.props.url,
+                                  "pnTags": self.tags});
+                self.status = Some(String::from("Capturing..."));
+                self.post(query, body, PopupMsg::Submitted);
+                true
+            }
+            PopupMsg::Submitted(success) => {
+                // a failed capture can be tried again
+                self.submitting = false;
+                self.submitted = success;
+                self.status = Some(String::from(if success {
+                    "Captured"
+                } else {
+                    "Capture failed, is the server running?"
+                }));
+                true
+            }
+            PopupMsg::ToggleCompleted => {
+                let entry_id = match &self.existing {
+                    Some(Some(entry)) => entry.entry_id,
+                    _ => return false,
+                };
+                let state = !self.completed.unwrap_or(false);
+                let payload = CompletedPayload { entry_id, state };
+                let query = format!("http://{}/submit/completed", server);
+                let body = json!({"pcEntryID": payload.entry_id,
+                                  "pcState": payload.state});
+                self.completed = Some(state);
+                self.post(query, body, PopupMsg::CompletedSent);
+                true
+            }
+            PopupMsg::CompletedSent(success) => {
+                if !success {
+                    self.completed = self.completed.map(|c| !c);
+                    self.status = Some(String::from("Could not update completion"));
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="popup">
+                <div class="popup-title">
+                    <span style="color:#bb7b52">{"Open"}</span><span style="color:#000000">{"Memex"}</span>
+                </div>
+                <div class="popup-page">{ &self.props.title }</div>
+                {
+                    match &self.existing {
+                        None => html! { <div class="popup-status">{"Checking..."}</div> },
+                        Some(Some(entry)) => self.view_existing(entry),
+                        Some(None) => self.view_capture(),
+                    }
+                }
+                {
+                    match &self.status {
+                        Some(status) => html! { <div class="popup-status">{ status }</div> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+}
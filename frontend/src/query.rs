@@ -0,0 +1,391 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use urlencoding::encode;
+
+// Search box query language:
+//
+//   tag:ml -tag:done site:arxiv.org after:2021-01-01 before:2021-05-01
+//   is:completed -is:completed is:note is:link match:all "quoted phrase" words
+//
+// Everything that isn't an operator is free text, sent to the ranked search.
+// `after:` includes its day and `before:` excludes it. `tag:ml/*` matches ml
+// and every tag under it (ml/transformers, ml:vision), the server expands it.
+// Entries with any of the `tag:`s match, `match:all` asks for every one.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    Note,
+    Link,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GalleryQuery {
+    // free words and quoted phrases
+    pub terms: Vec<String>,
+    pub tags: Vec<String>,
+    // every tag instead of any, absent from queries saved before it existed
+    #[serde(default)]
+    pub all_tags: bool,
+    pub exclude_tags: Vec<String>,
+    pub site: Option<String>,
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+    pub completed: Option<bool>,
+    pub kind: Option<EntryKind>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    // char offset of the offending token
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}: {}", self.position + 1, self.message)
+    }
+}
+
+// most entries one query asks the server for
+pub const GALLERY_LIMIT: usize = 150;
+
+fn error(position: usize, message: String) -> QueryError {
+    QueryError { position, message }
+}
+
+// Splits on whitespace outside double quotes, returning each token with its position.
+fn tokenize(input: &str) -> Result<Vec<(usize, String)>, QueryError> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut start = 0;
+    let mut quote: Option<usize> = None;
+    for (i, c) in input.chars().enumerate() {
+        if c.is_whitespace() && quote.is_none() {
+            if !current.is_empty() {
+                tokens.push((start, std::mem::take(&mut current)));
+            }
+            continue;
+        }
+        if current.is_empty() {
+            start = i;
+        }
+        if c == '"' {
+            quote = match quote {
+                Some(_) => None,
+                None => Some(i),
+            };
+        }
+        current.push(c);
+    }
+    if let Some(position) = quote {
+        return Err(error(position, String::from("unterminated quote")));
+    }
+    if !current.is_empty() {
+        tokens.push((start, current));
+    }
+    Ok(tokens)
+}
+
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+fn parse_date(position: usize, key: &str, value: &str) -> Result<NaiveDate, QueryError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| error(position, format!("{}: expects a date like 2021-05-01", key)))
+}
+
+pub fn parse(input: &str) -> Result<GalleryQuery, QueryError> {
+    let mut query = GalleryQuery::default();
+    for (position, token) in tokenize(input)? {
+        let (negated, body) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token.as_str()),
+        };
+        let (key, value) = match body.split_once(':') {
+            Some((key, value)) if ["tag", "site", "before", "after", "is", "match"].contains(&key) => {
+                (key, unquote(value))
+            }
+            // not an operator, urls and times have colons too
+            _ => {
+                let term = unquote(&token);
+                if !term.is_empty() {
+                    query.terms.push(term);
+                }
+                continue;
+            }
+        };
+        if value.is_empty() {
+            return Err(error(position, format!("{}: needs a value", key)));
+        }
+        match (key, negated) {
+            ("tag", false) => query.tags.push(value),
+            ("tag", true) => query.exclude_tags.push(value),
+            ("is", _) => match (value.as_str(), negated) {
+                ("completed", _) => query.completed = Some(!negated),
+                ("note", false) | ("link", false) => {
+                    let kind = if value == "note" { EntryKind::Note } else { EntryKind::Link };
+                    if query.kind.is_some_and(|k| k != kind) {
+                        return Err(error(position, String::from("is:note and is:link can't be combined")));
+                    }
+                    query.kind = Some(kind);
+                }
+                ("note", true) | ("link", true) => {
+                    return Err(error(position, format!("-is:{} is not supported", value)))
+                }
+                _ => {
+                    return Err(error(
+                        position,
+                        format!("unknown is:{}, expected completed, note or link", value),
+                    ))
+                }
+            },
+            (_, true) => return Err(error(position, format!("-{}: is not supported", key))),
+            ("match", false) => match value.as_str() {
+                "all" => query.all_tags = true,
+                "any" => query.all_tags = false,
+                _ => return Err(error(position, format!("unknown match:{}, expected all or any", value))),
+            },
+            ("site", false) => query.site = Some(value.to_lowercase()),
+            ("after", false) => query.after = Some(parse_date(position, key, &value)?),
+            ("before", false) => query.before = Some(parse_date(position, key, &value)?),
+            _ => unreachable!(),
+        }
+    }
+    if let (Some(after), Some(before)) = (query.after, query.before) {
+        if after >= before {
+            return Err(error(0, String::from("after: must be earlier than before:")));
+        }
+    }
+    Ok(query)
+}
+
+fn quote(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+impl GalleryQuery {
+    // free text goes to the ranked search, filters alone list the gallery
+    pub fn is_search(&self) -> bool {
+        !self.terms.is_empty()
+    }
+
+    // the free text as the server's `q` param, phrases kept quoted
+    pub fn text(&self) -> String {
+        self.terms.iter().map(|term| quote(term)).collect::<Vec<String>>().join(" ")
+    }
+
+    fn filter_params(&self) -> String {
+        let mut params = String::new();
+        for tag in self.tags.iter() {
+            params.push_str(&format!("&tag={}", encode(tag)));
+        }
+        if self.all_tags {
+            params.push_str("&alltags=true");
+        }
+        for tag in self.exclude_tags.iter() {
+            params.push_str(&format!("&excludeTag={}", encode(tag)));
+        }
+        if let Some(site) = &self.site {
+            params.push_str(&format!("&site={}", encode(site)));
+        }
+        if let Some(completed) = self.completed {
+            params.push_str(&format!("&completed={}", completed));
+        }
+        match self.kind {
+            Some(EntryKind::Note) => params.push_str("&kind=note"),
+            Some(EntryKind::Link) => params.push_str("&kind=link"),
+            None => (),
+        }
+        if let Some(after) = self.after {
+            params.push_str(&format!("&startDate={}", after.format("%Y-%m-%d")));
+        }
+        // the server's endDate is inclusive
+        if let Some(before) = self.before {
+            let end = before - Duration::days(1);
+            params.push_str(&format!("&endDate={}", end.format("%Y-%m-%d")));
+        }
+        params
+    }
+
+    // how many entries the query matches, past the gallery limit
+    pub fn count_url(&self, server: &str) -> String {
+        format!("http://{}/count?q={}{}", server, encode(&self.text()), self.filter_params())
+    }
+
+    pub fn to_url(&self, server: &str) -> String {
+        if self.is_search() {
+            format!(
+                "http://{}/search/results?q={}&limit={}{}",
+                server,
+                encode(&self.text()),
+                GALLERY_LIMIT,
+                self.filter_params()
+            )
+        } else {
+            format!(
+                "http://{}/all/cache?sort=time&limit={}{}",
+                server,
+                GALLERY_LIMIT,
+                self.filter_params()
+            )
+        }
+    }
+}
+
+// Back to search box text, so `parse(&query.to_string()) == Ok(query)`.
+impl fmt::Display for GalleryQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = vec![];
+        parts.extend(self.tags.iter().map(|tag| format!("tag:{}", quote(tag))));
+        if self.all_tags {
+            parts.push(String::from("match:all"));
+        }
+        parts.extend(self.exclude_tags.iter().map(|tag| format!("-tag:{}", quote(tag))));
+        if let Some(site) = &self.site {
+            parts.push(format!("site:{}", site));
+        }
+        if let Some(after) = self.after {
+            parts.push(format!("after:{}", after.format("%Y-%m-%d")));
+        }
+        if let Some(before) = self.before {
+            parts.push(format!("before:{}", before.format("%Y-%m-%d")));
+        }
+        match self.completed {
+            Some(true) => parts.push(String::from("is:completed")),
+            Some(false) => parts.push(String::from("-is:completed")),
+            None => (),
+        }
+        match self.kind {
+            Some(EntryKind::Note) => parts.push(String::from("is:note")),
+            Some(EntryKind::Link) => parts.push(String::from("is:link")),
+            None => (),
+        }
+        if !self.terms.is_empty() {
+            parts.push(self.text());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn empty_input_is_the_default_query() {
+        assert_eq!(parse("").unwrap(), GalleryQuery::default());
+        assert_eq!(parse("   ").unwrap(), GalleryQuery::default());
+    }
+
+    #[test]
+    fn free_words_are_terms() {
+        let query = parse("attention  transformers").unwrap();
+        assert_eq!(query.terms, vec!["attention", "transformers"]);
+        assert!(query.is_search());
+    }
+
+    #[test]
+    fn quoted_phrases_stay_together() {
+        let query = parse("\"machine learning\" tag:\"deep learning\"").unwrap();
+        assert_eq!(query.terms, vec!["machine learning"]);
+        assert_eq!(query.tags, vec!["deep learning"]);
+    }
+
+    #[test]
+    fn tags_and_excluded_tags() {
+        let query = parse("tag:ml tag:nlp -tag:done").unwrap();
+        assert_eq!(query.tags, vec!["ml", "nlp"]);
+        assert_eq!(query.exclude_tags, vec!["done"]);
+        assert!(!query.is_search());
+    }
+
+    #[test]
+    fn tags_match_any_unless_asked() {
+        let any = parse("tag:ml tag:nlp").unwrap();
+        assert!(!any.all_tags);
+        assert!(!any.to_url("localhost:3000").contains("alltags"));
+
+        let all = parse("tag:ml tag:nlp match:all").unwrap();
+        assert!(all.all_tags);
+        assert!(all.to_url("localhost:3000").contains("&tag=ml&tag=nlp&alltags=true"));
+        assert_eq!(all.to_string(), "tag:ml tag:nlp match:all");
+        assert!(parse("match:some").is_err());
+    }
+
+    #[test]
+    fn site_and_dates() {
+        let query = parse("site:ArXiv.org after:2021-01-01 before:2021-05-01").unwrap();
+        assert_eq!(query.site, Some(String::from("arxiv.org")));
+        assert_eq!(query.after, Some(date("2021-01-01")));
+        assert_eq!(query.before, Some(date("2021-05-01")));
+    }
+
+    #[test]
+    fn is_operators() {
+        assert_eq!(parse("is:completed").unwrap().completed, Some(true));
+        assert_eq!(parse("-is:completed").unwrap().completed, Some(false));
+        assert_eq!(parse("is:note").unwrap().kind, Some(EntryKind::Note));
+        assert_eq!(parse("is:link").unwrap().kind, Some(EntryKind::Link));
+    }
+
+    #[test]
+    fn urls_and_unknown_keys_are_text() {
+        let query = parse("https://arxiv.org/abs/1 foo:bar").unwrap();
+        assert_eq!(query.terms, vec!["https://arxiv.org/abs/1", "foo:bar"]);
+        assert_eq!(parse("-").unwrap().terms, vec!["-"]);
+    }
+
+    #[test]
+    fn errors_point_at_the_token() {
+        let err = parse("tag:ml before:yesterday").unwrap_err();
+        assert_eq!(err.position, 7);
+        assert!(err.message.contains("before:"));
+
+        assert_eq!(parse("ml \"open phrase").unwrap_err().position, 3);
+        assert_eq!(parse("tag:").unwrap_err().message, "tag: needs a value");
+        assert!(parse("is:archived").unwrap_err().message.contains("unknown is:archived"));
+        assert!(parse("-site:example.com").is_err());
+        assert!(parse("-is:note").is_err());
+        assert!(parse("is:note is:link").is_err());
+        assert!(parse("after:2021-05-01 before:2021-01-01").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let input = "tag:ml -tag:\"to read\" site:arxiv.org after:2021-01-01 before:2021-05-01 \
+                     -is:completed is:link \"neural nets\" survey";
+        let query = parse(input).unwrap();
+        assert_eq!(parse(&query.to_string()).unwrap(), query);
+        assert_eq!(query.to_string(), input);
+    }
+
+    #[test]
+    fn gallery_url_carries_filters() {
+        let query = parse("tag:ml -tag:done is:completed before:2021-05-01").unwrap();
+        assert_eq!(
+            query.to_url("localhost:3000"),
+            "http://localhost:3000/all/cache?sort=time&limit=150\
+             &tag=ml&excludeTag=done&completed=true&endDate=2021-04-30"
+        );
+    }
+
+    #[test]
+    fn search_url_quotes_phrases() {
+        let query = parse("\"neural nets\" site:arxiv.org").unwrap();
+        assert_eq!(
+            query.to_url("localhost:3000"),
+            "http://localhost:3000/search/results?q=%22neural%20nets%22&limit=150&site=arxiv.org"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use yew::format::Json;
+use yew::prelude::*;
+use yew::services::storage::{Area, StorageService};
+
+// Search input with a dropdown of past searches kept in local storage.
+
+const HISTORY_KEY: &str = "openmemex.search_history";
+const HISTORY_LIMIT: usize = 50;
+// entries shown in each dropdown section
+const SHOWN: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryItem {
+    pub query: String,
+    pub count: u32,
+    // ms since epoch
+    pub last_used: f64,
+}
+
+#[derive(Debug)]
+pub enum SearchBoxMsg {
+    Edit(String),
+    KeyDown(KeyboardEvent),
+    Focus,
+    Blur,
+    Pick(String),
+    Remove(String),
+}
+
+pub struct SearchBox {
+    link: ComponentLink<Self>,
+    props: Props,
+    storage: Option<StorageService>,
+    history: Vec<HistoryItem>,
+    open: bool,
+    // position in the dropdown, recent items first then frequent ones
+    highlighted: Option<usize>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub value: String,
+    pub edit_callback: Callback<String>,
+    pub submit_callback: Callback<String>,
+}
+
+impl SearchBox {
+    fn save(&mut self) {
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(HISTORY_KEY, Json(&self.history));
+        }
+    }
+
+    fn record(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        let now = js_sys::Date::now();
+        match self.history.iter_mut().find(|item| item.query == query) {
+            Some(item) => {
+                item.count += 1;
+                item.last_used = now;
+            }
+            None => self.history.push(HistoryItem {
+                query: query.to_string(),
+                count: 1,
+                last_used: now,
+            }),
+        }
+        // forget the least recently used first
+        self.history
+            .sort_by(|a, b| b.last_used.partial_cmp(&a.last_used).unwrap_or(std::cmp::Ordering::Equal));
+        self.history.truncate(HISTORY_LIMIT);
+        self.save();
+    }
+
+    // (recent, frequent) matching what is typed, without duplicates between them
+    fn suggestions(&self) -> (Vec<&HistoryItem>, Vec<&HistoryItem>) {
+        let typed = self.props.value.trim().to_lowercase();
+        let mut matching: Vec<&HistoryItem> = self
+            .history
+            .iter()
+            .filter(|item| item.query != self.props.value.trim())
+            .filter(|item| item.query.to_lowercase().contains(&typed))
+            .collect();
+        matching.sort_by(|a, b| b.last_used.partial_cmp(&a.last_used).unwrap_or(std::cmp::Ordering::Equal));
+        let recent: Vec<&HistoryItem> = matching.iter().take(SHOWN).copied().collect();
+        matching.sort_by_key(|item| std::cmp::Reverse(item.count));
+        let frequent = matching
+            .into_iter()
+            .filter(|item| item.count > 1 && !recent.iter().any(|r| r.query == item.query))
+            .take(SHOWN)
+            .collect();
+        (recent, frequent)
+    }
+
+    fn suggestion_queries(&self) -> Vec<String> {
+        let (recent, frequent) = self.suggestions();
+        recent.iter().chain(frequent.iter()).map(|item| item.query.clone()).collect()
+    }
+
+    fn submit(&mut self, query: String) {
+        self.record(&query);
+        self.open = false;
+        self.highlighted = None;
+        self.props.submit_callback.emit(query);
+    }
+
+    fn view_section(&self, title: &str, items: &[&HistoryItem], offset: usize) -> Html {
+        if items.is_empty() {
+            return html! {};
+        }
+        html! {
+            <>
+                <div class="search-history-title">{ title }</div>
+                {
+                    for items.iter().enumerate().map(|(i, item)| {
+                        let class = if self.highlighted == Some(offset + i) {
+                            "search-history-item search-history-highlighted"
+                        } else {
+                            "search-history-item"
+                        };
+                        let pick = item.query.clone();
+                        let remove = item.query.clone();
+                        // mousedown fires before the input's blur closes the dropdown
+                        html! {
+                            <div class=class
+                                onmousedown=self.link.callback(move |_| SearchBoxMsg::Pick(pick.clone()))>
+                                { &item.query }
+                                <span class="search-history-remove" title="remove from history"
+                                    onmousedown=self.link.callback(move |e: MouseEvent| {
+                                        e.prevent_default();
+                                        e.stop_propagation();
+                                        SearchBoxMsg::Remove(remove.clone())
+                                    })>
+                                    {"×"}
+                                </span>
+                            </div>
+                        }
+                    })
+                }
+            </>
+        }
+    }
+
+    fn view_dropdown(&self) -> Html {
+        if !self.open {
+            return html! {};
+        }
+        let (recent, frequent) = self.suggestions();
+        if recent.is_empty() && frequent.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class="search-history shadow-sm bg-white rounded">
+                { self.view_section("Recent", &recent, 0) }
+                { self.view_section("Frequent", &frequent, recent.len()) }
+            </div>
+        }
+    }
+}
+
+impl Component for SearchBox {
+    type Message = SearchBoxMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let storage = StorageService::new(Area::Local).ok();
+        let history = match &storage {
+            Some(storage) => {
+                let Json(history) = storage.restore(HISTORY_KEY);
+                history.unwrap_or_default()
+            }
+            None => vec![],
+        };
+        Self {
+            link,
+            props,
+            storage,
+            history,
+            open: false,
+            highlighted: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            SearchBoxMsg::Edit(value) => {
+                self.open = true;
+                self.highlighted = None;
+                self.props.edit_callback.emit(value);
+                false
+            }
+            SearchBoxMsg::KeyDown(keypress) => {
+                let count = self.suggestion_queries().len();
+                match keypress.key().as_str() {
+                    "ArrowDown" if count > 0 => {
+                        keypress.prevent_default();
+                        self.open = true;
+                        self.highlighted = Some(self.highlighted.map_or(0, |i| (i + 1) % count));
+                        true
+                    }
+                    "ArrowUp" if count > 0 => {
+                        keypress.prevent_default();
+                        self.highlighted = Some(self.highlighted.map_or(count - 1, |i| (i + count - 1) % count));
+                        true
+                    }
+                    "Escape" => {
+                        self.open = false;
+                        self.highlighted = None;
+                        true
+                    }
+                    "Delete" if keypress.shift_key() => {
+                        // shift+delete removes the highlighted entry, like browser history
+                        if let Some(query) = self.highlighted.and_then(|i| self.suggestion_queries().get(i).cloned()) {
+                            self.link.send_message(SearchBoxMsg::Remove(query));
+                        }
+                        false
+                    }
+                    "Enter" => {
+                        let query = self
+                            .highlighted
+                            .filter(|_| self.open)
+                            .and_then(|i| self.suggestion_queries().get(i).cloned())
+                            .unwrap_or_else(|| self.props.value.clone());
+                        self.submit(query);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            SearchBoxMsg::Focus => {
+                self.open = true;
+                true
+            }
+            SearchBoxMsg::Blur => {
+                self.open = false;
+                self.highlighted = None;
+                true
+            }
+            SearchBoxMsg::Pick(query) => {
+                self.submit(query);
+                true
+            }
+            SearchBoxMsg::Remove(query) => {
+                self.history.retain(|item| item.query != query);
+                self.save();
+                let count = self.suggestion_queries().len();
+                self.highlighted = self.highlighted.filter(|i| *i < count);
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="search-box">
+                <input type="text" class="search-input shadow-sm p-3 mb-5 bg-white rounded" placeholder="Search" accesskey="/"
+                    title="tag:ml -tag:done site:arxiv.org after:2021-01-01 before:2021-05-01 is:completed is:note is:link \"a phrase\""
+                    autocomplete="off"
+                    value=self.props.value.clone()
+                    oninput=self.link.callback(|e: InputData| SearchBoxMsg::Edit(e.value))
+                    onkeydown=self.link.callback(SearchBoxMsg::KeyDown)
+                    onfocus=self.link.callback(|_| SearchBoxMsg::Focus)
+                    onblur=self.link.callback(|_| SearchBoxMsg::Blur)
+                />
+                { self.view_dropdown() }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::api::*;
+use crate::app_router::*;
+use url::*;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+pub type Link = RouterAnchor<AppRoute>;
+
+#[derive(Debug)]
+pub enum SearchResultsMsg {
+    ResultClick(Cache),
+}
+
+pub struct SearchResults {
+    link: ComponentLink<Self>,
+    props: Props,
+    entry_id_click: Option<i32>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub results: Vec<SearchResult>,
+    // words and phrases searched for, highlighted in the snippets
+    pub terms: Vec<String>,
+    pub card_click_callback: Callback<Option<Cache>>,
+    // the entry open in Detail
+    #[prop_or_default]
+    pub selected_id: Option<i32>,
+}
+
+fn field_label(field: &str) -> &str {
+    match field {
+        "title" => "title",
+        "note" => "note",
+        "page" => "page text",
+        "ocr" => "OCR",
+        "tag" => "tag",
+        "url" => "url",
+        _ => field,
+    }
+}
+
+// Splits text into (segment, is_match) runs, matching any term case-insensitively.
+// Works on chars so multi-byte text doesn't split mid character.
+fn highlight(text: &str, terms: &[Vec<char>]) -> Vec<(String, bool)> {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut segments: Vec<(String, bool)> = vec![];
+    let mut plain = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let hit = terms
+            .iter()
+            .filter(|term| !term.is_empty() && lower[i..].starts_with(term))
+            .map(|term| term.len())
+            .max();
+        match hit {
+            Some(len) => {
+                if !plain.is_empty() {
+                    segments.push((std::mem::take(&mut plain), false));
+                }
+                segments.push((chars[i..i + len].iter().collect(), true));
+                i += len;
+            }
+            None => {
+                plain.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if !plain.is_empty() {
+        segments.push((plain, false));
+    }
+    segments
+}
+
+impl SearchResults {
+    fn view_result(&self, result: &SearchResult, terms: &[Vec<char>]) -> Html {
+        let entry = result.entry.clone();
+        let site = entry
+            .url
+            .as_ref()
+            .and_then(|url| Url::parse(url).ok())
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_default();
+        let title = entry.content.clone().or_else(|| entry.url.clone()).unwrap_or_default();
+        let row_class = if Some(entry.entry_id) == self.entry_id_click {
+            "search-result shadow-none p-3 mb-3 bg-light rounded"
+        } else {
+            "search-result shadow-sm p-3 mb-3 bg-white rounded"
+        };
+        let click_entry = entry.clone();
+        html! {
+            <div class=row_class onclick=self.link.callback(move |_| SearchResultsMsg::ResultClick(click_entry.clone()))>
+                { &entry.date }
+                <font color="grey">{ format!(" {}", site) }</font>
+                <span class="search-field-badge">{ field_label(&result.field) }</span>
+                <div class="search-result-title">
+                {
+                    match entry.url.as_ref() {
+                        Some(url) => html! { <a href=url.to_string()>{ title }</a> },
+                        None => html! { { title } },
+                    }
+                }
+                </div>
+                <Link route=AppRoute::DetailById(entry.entry_id)>
+                    <div class="search-snippet">
+                    {
+                        for highlight(&result.snippet, terms).into_iter().map(|(text, hit)| {
+                            if hit {
+                                html! { <mark>{ text }</mark> }
+                            } else {
+                                html! { { text } }
+                            }
+                        })
+                    }
+                    </div>
+                </Link>
+            </div>
+        }
+    }
+}
+
+impl Component for SearchResults {
+    type Message = SearchResultsMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            entry_id_click: props.selected_id,
+            link,
+            props,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.entry_id_click = props.selected_id;
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            SearchResultsMsg::ResultClick(entry) => {
+                if self.entry_id_click == Some(entry.entry_id) {
+                    self.entry_id_click = None;
+                    self.props.card_click_callback.emit(None);
+                } else {
+                    self.entry_id_click = Some(entry.entry_id);
+                    self.props.card_click_callback.emit(Some(entry));
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        if self.props.results.is_empty() {
+            return html! { <div class="search-results">{"No matches"}</div> };
+        }
+        let terms: Vec<Vec<char>> = self
+            .props
+            .terms
+            .iter()
+            .map(|term| term.chars().map(|c| c.to_ascii_lowercase()).collect())
+            .collect();
+        html! {
+            <div class="search-results">
+                <font color="grey">{ format!("{} results", self.props.results.len()) }</font>
+                { for self.props.results.iter().map(|result| self.view_result(result, &terms)) }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// Local IndexedDB store backing the offline-first reads and the sync outbox.
+//
+// Every value is kept as a JSON string under a string key, one object store
+// per kind of data. Operations share one connection, opened by whichever
+// comes first, so callers never have to wait on an open handle.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+use yew::services::DialogService;
+use yew::Callback;
+
+const DB_NAME: &str = "openmemex";
+const DB_VERSION: u32 = 3;
+
+// gallery query url -> Vec<Cache>
+pub const QUERIES: &str = "queries";
+// tag query url -> Vec<String>
+pub const TAGS: &str = "tags";
+// timestamp query url -> Vec<Timestamp>
+pub const TIMESTAMPS: &str = "timestamps";
+// outbox key -> OutboxItem
+pub const OUTBOX: &str = "outbox";
+// collection name -> Collection, added in version 2
+pub const COLLECTIONS: &str = "collections";
+
+const STORES: [&str; 5] = [QUERIES, TAGS, TIMESTAMPS, OUTBOX, COLLECTIONS];
+// stores older versions made that nothing reads any more, dropped in version 3
+const RETIRED: [&str; 1] = ["entries"];
+
+type Waiter = Box<dyn FnOnce(Option<IdbDatabase>)>;
+
+enum Connection {
+    Closed,
+    // operations waiting for the open to finish
+    Opening(Vec<Waiter>),
+    Open(IdbDatabase),
+}
+
+thread_local! {
+    static CONNECTION: RefCell<Connection> = const { RefCell::new(Connection::Closed) };
+}
+
+// `f` gets None when the store can't be used (private mode, old browsers) so
+// reads can still answer with nothing.
+fn open(f: impl FnOnce(Option<IdbDatabase>) + 'static) {
+    let db = CONNECTION.with(|connection| match &*connection.borrow() {
+        Connection::Open(db) => Some(db.clone()),
+        _ => None,
+    });
+    if let Some(db) = db {
+        return f(Some(db));
+    }
+    let first = CONNECTION.with(|connection| {
+        let mut connection = connection.borrow_mut();
+        match &mut *connection {
+            Connection::Opening(waiters) => {
+                waiters.push(Box::new(f));
+                false
+            }
+            _ => {
+                *connection = Connection::Opening(vec![Box::new(f)]);
+                true
+            }
+        }
+    });
+    if first {
+        connect();
+    }
+}
+
+// hands the open result to everything waiting on it
+fn opened(db: Option<IdbDatabase>) {
+    let waiters = CONNECTION.with(|connection| {
+        let next = match &db {
+            Some(db) => Connection::Open(db.clone()),
+            None => Connection::Closed,
+        };
+        match connection.replace(next) {
+            Connection::Opening(waiters) => waiters,
+            _ => vec![],
+        }
+    });
+    for waiter in waiters {
+        waiter(db.clone());
+    }
+}
+
+fn connect() {
+    let factory = match web_sys::window().and_then(|w| w.indexed_db().ok().flatten()) {
+        Some(factory) => factory,
+        None => {
+            log::info!("indexeddb is not available, local store disabled");
+            return opened(None);
+        }
+    };
+    let request = match factory.open_with_u32(DB_NAME, DB_VERSION) {
+        Ok(request) => request,
+        Err(error) => {
+            log::info!("could not open local store: {:?}", error);
+            return opened(None);
+        }
+    };
+
+    let upgrade_request = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        if let Ok(db) = upgrade_request.result().and_then(|r| r.dyn_into::<IdbDatabase>()) {
+            let existing = db.object_store_names();
+            for name in STORES.iter() {
+                if !existing.contains(name) {
+                    if let Err(error) = db.create_object_store(name) {
+                        log::info!("could not create object store {}: {:?}", name, error);
+                    }
+                }
+            }
+            for name in RETIRED.iter() {
+                if existing.contains(name) {
+                    if let Err(error) = db.delete_object_store(name) {
+                        log::info!("could not delete object store {}: {:?}", name, error);
+                    }
+                }
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    // a tab still running older code holds the old version open
+    let on_blocked = Closure::once_into_js(move || {
+        log::info!("local store upgrade blocked by another tab");
+        DialogService::alert("OpenMemex was updated. Close its other tabs so it can finish upgrading.");
+    });
+    request.set_onblocked(Some(on_blocked.unchecked_ref()));
+
+    on_settled(&request, move |result| match result.map(|r| r.dyn_into::<IdbDatabase>()) {
+        Some(Ok(db)) => {
+            // let a newer version in another tab upgrade, the next operation reopens
+            let closing = db.clone();
+            let on_version_change = Closure::once_into_js(move || {
+                log::info!("local store closed for an upgrade");
+                closing.close();
+                CONNECTION.with(|connection| {
+                    let open = matches!(&*connection.borrow(), Connection::Open(_));
+                    if open {
+                        connection.replace(Connection::Closed);
+                    }
+                });
+            });
+            db.set_onversionchange(Some(on_version_change.unchecked_ref()));
+            opened(Some(db))
+        }
+        _ => {
+            log::info!("local store open error");
+            opened(None)
+        }
+    });
+}
+
+// Calls `f` once with the request result, or None if the request failed.
+fn on_settled(request: &IdbRequest, f: impl FnOnce(Option<JsValue>) + 'static) {
+    let f = Rc::new(RefCell::new(Some(f)));
+    let success_f = f.clone();
+    let success_request = request.clone();
+    let on_success = Closure::once_into_js(move || {
+        if let Some(f) = success_f.borrow_mut().take() {
+            f(success_request.result().ok())
+        }
+    });
+    let on_error = Closure::once_into_js(move || {
+        if let Some(f) = f.borrow_mut().take() {
+            f(None)
+        }
+    });
+    request.set_onsuccess(Some(on_success.unchecked_ref()));
+    request.set_onerror(Some(on_error.unchecked_ref()));
+}
+
+fn with_store(
+    store: &'static str,
+    mode: IdbTransactionMode,
+    f: impl FnOnce(Option<IdbObjectStore>) + 'static,
+) {
+    open(move |db| {
+        let object_store = db.and_then(|db| {
+            db.transaction_with_str_and_mode(store, mode)
+                .and_then(|tx| tx.object_store(store))
+                .map_err(|error| {
+                    log::info!("local store transaction error on {}: {:?}", store, error)
+                })
+                .ok()
+        });
+        f(object_store)
+    });
+}
+
+// `f` gets None if the request could not be made
+fn on_result(request: Option<Result<IdbRequest, JsValue>>, f: impl FnOnce(Option<JsValue>) + 'static) {
+    match request {
+        Some(Ok(request)) => on_settled(&request, f),
+        Some(Err(error)) => {
+            log::info!("local store request error: {:?}", error);
+            f(None)
+        }
+        None => f(None),
+    }
+}
+
+pub fn put(store: &'static str, key: &str, value: &str) {
+    let key = JsValue::from_str(key);
+    let value = JsValue::from_str(value);
+    with_store(store, IdbTransactionMode::Readwrite, move |object_store| {
+        if let Some(Err(error)) = object_store.map(|s| s.put_with_key(&value, &key)) {
+            log::info!("local store put error: {:?}", error);
+        }
+    });
+}
+
+pub fn delete(store: &'static str, key: &str) {
+    let key = JsValue::from_str(key);
+    with_store(store, IdbTransactionMode::Readwrite, move |object_store| {
+        if let Some(Err(error)) = object_store.map(|s| s.delete(&key)) {
+            log::info!("local store delete error: {:?}", error);
+        }
+    });
+}
+
+pub fn get(store: &'static str, key: &str, callback: Callback<Option<String>>) {
+    let key = JsValue::from_str(key);
+    with_store(store, IdbTransactionMode::Readonly, move |object_store| {
+        on_result(object_store.map(|s| s.get(&key)), move |value| {
+            callback.emit(value.and_then(|v| v.as_string()))
+        });
+    });
+}
+
+// Values come back ordered by key.
+pub fn get_all(store: &'static str, callback: Callback<Vec<String>>) {
+    with_store(store, IdbTransactionMode::Readonly, move |object_store| {
+        on_result(object_store.map(|s| s.get_all()), move |value| {
+            let values = match value {
+                Some(value) => js_sys::Array::from(&value)
+                    .iter()
+                    .filter_map(|v| v.as_string())
+                    .collect(),
+                None => vec![],
+            };
+            callback.emit(values)
+        });
+    });
+}
+
+// Reads a value and maybe replaces it in one transaction, so tabs racing on
+// the same key see each other's writes. `f` gets the current value and
+// returns the one to write, if any, and what to answer; the callback gets
+// None when the store can't be used or the read failed.
+pub fn update<R: 'static>(
+    store: &'static str,
+    key: &str,
+    f: impl FnOnce(Option<String>) -> (Option<String>, R) + 'static,
+    callback: Callback<Option<R>>,
+) {
+    let key = JsValue::from_str(key);
+    with_store(store, IdbTransactionMode::Readwrite, move |object_store| {
+        let object_store = match object_store {
+            Some(object_store) => object_store,
+            None => return callback.emit(None),
+        };
+        let request = object_store.get(&key);
+        on_result(Some(request), move |value| {
+            let value = match value {
+                Some(value) => value.as_string(),
+                None => return callback.emit(None),
+            };
+            let (write, answer) = f(value);
+            match write {
+                Some(value) => {
+                    let request = object_store.put_with_key(&JsValue::from_str(&value), &key);
+                    on_result(Some(request), move |_| callback.emit(Some(answer)));
+                }
+                None => callback.emit(Some(answer)),
+            }
+        });
+    });
+}
+
+pub fn put_json<T: Serialize>(store: &'static str, key: &str, value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => put(store, key, &json),
+        Err(error) => log::info!("local store serialize error: {}", error),
+    }
+}
+
+pub fn get_json<T: DeserializeOwned + 'static>(
+    store: &'static str,
+    key: &str,
+    callback: Callback<Option<T>>,
+) {
+    get(
+        store,
+        key,
+        Callback::from(move |value: Option<String>| {
+            callback.emit(value.and_then(|json| serde_json::from_str(&json).ok()))
+        }),
+    );
+}
+
+pub fn update_json<T: Serialize + DeserializeOwned + 'static, R: 'static>(
+    store: &'static str,
+    key: &str,
+    f: impl FnOnce(Option<T>) -> (Option<T>, R) + 'static,
+    callback: Callback<Option<R>>,
+) {
+    update(
+        store,
+        key,
+        move |value| {
+            let (write, answer) = f(value.and_then(|json| serde_json::from_str(&json).ok()));
+            (write.and_then(|value| serde_json::to_string(&value).ok()), answer)
+        },
+        callback,
+    );
+}
+
+pub fn get_all_json<T: DeserializeOwned + 'static>(store: &'static str, callback: Callback<Vec<T>>) {
+    get_all(
+        store,
+        Callback::from(move |values: Vec<String>| {
+            callback.emit(
+                values
+                    .iter()
+                    .filter_map(|json| serde_json::from_str(json).ok())
+                    .collect(),
+            )
+        }),
+    );
+}
@@ -0,0 +1,485 @@
+// Outbox of writes waiting to reach the server.
+//
+// Components enqueue writes through `SyncAgent::dispatcher()` instead of
+// posting directly, so captures made while the server is down are kept in
+// the local store and replayed in order once it is reachable again.
+//
+// Every tab runs its own agent over the same store, so before sending an
+// item an agent claims it there; items another tab holds are left to it.
+
+use crate::api::*;
+use crate::store;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use yew::agent::{Agent, AgentLink, Context, HandlerId};
+use yew::format::{Json, Nothing, Text};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::utils::host;
+
+const RETRY_SECONDS: u64 = 30;
+// a claim older than this belongs to a tab that went away mid-send
+const CLAIM_MS: f64 = 5.0 * 60.0 * 1000.0;
+
+static OUTBOX_SEQ: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum OutboxOp {
+    Note(AddNotePayload),
+    // more tags on an entry that already exists
+    Tags(TagsPayload),
+    Untag(TagsPayload),
+    // replaces the content of an existing entry
+    Edit(EditPayload),
+    // a note followed by an annotation on the entry it creates
+    Capture {
+        note: AddNotePayload,
+        annotation: String,
+    },
+    // completed is a flag with no history on the server, so the last toggle
+    // sent wins, whichever tab or device it came from
+    Completed(CompletedPayload),
+    // base is the annotation the user started editing from
+    Annotation {
+        payload: AnnotationPayload,
+        base: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OutboxItem {
+    pub key: String,
+    pub op: OutboxOp,
+    // the agent sending this item and when it claimed it
+    #[serde(default)]
+    pub claim: Option<(String, f64)>,
+}
+
+#[derive(Debug)]
+pub enum Claim {
+    Claimed,
+    // another tab is sending it
+    Taken,
+    // another tab already sent it
+    Gone,
+}
+
+#[derive(Debug)]
+pub enum SyncRequest {
+    Enqueue(OutboxOp),
+    Flush,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyncStatus {
+    Pending(usize),
+    // entry id whose annotation was also edited on the server
+    Conflict(i32),
+    // key of a write just enqueued, sent only to the component that asked
+    Queued(String),
+    // the write with this key reached the server
+    Sent(String),
+    // the server refused a write, it was dropped instead of retried
+    Rejected(OutboxItem, u16),
+}
+
+#[derive(Debug)]
+pub enum SyncMsg {
+    Loaded(Vec<OutboxItem>),
+    Tick,
+    // None when the store can't be used, then there is nobody to share with
+    Claimed(String, Option<Claim>),
+    // responses carry their http status, see `retryable`
+    ReceiveCompleted(u16, Result<Vec<bool>, anyhow::Error>),
+    ReceiveAnnotations(u16, Result<Vec<Annotation>, anyhow::Error>),
+    Sent(u16),
+    // the entry id the server assigned to a captured note
+    CaptureSent(u16, Option<i32>),
+}
+
+pub struct SyncAgent {
+    link: AgentLink<Self>,
+    subscribers: HashSet<HandlerId>,
+    queue: VecDeque<OutboxItem>,
+    task: Option<FetchTask>,
+    loaded: bool,
+    owner: String,
+    claiming: bool,
+    _interval: IntervalTask,
+}
+
+// network failures reach us as 408, like a timeout; other client errors
+// won't go away by sending the same write again
+fn retryable(status: u16) -> bool {
+    status == 408 || status == 429 || status >= 500
+}
+
+fn outbox_key() -> String {
+    let seq = OUTBOX_SEQ.fetch_add(1, Ordering::Relaxed);
+    format!("{:015}-{:06}", js_sys::Date::now() as u64, seq)
+}
+
+impl SyncAgent {
+    fn notify(&self, status: SyncStatus) {
+        for subscriber in self.subscribers.iter() {
+            self.link.respond(*subscriber, status.clone());
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.task.is_some() || self.claiming || !self.loaded {
+            return;
+        }
+        let key = match self.queue.front() {
+            Some(item) => item.key.clone(),
+            None => return,
+        };
+        self.claiming = true;
+        let owner = self.owner.clone();
+        let claim = move |item: Option<OutboxItem>| match item {
+            None => (None, Claim::Gone),
+            Some(OutboxItem {
+                claim: Some((other, at)),
+                ..
+            }) if other != owner && js_sys::Date::now() - at < CLAIM_MS => (None, Claim::Taken),
+            Some(item) => {
+                let claim = Some((owner, js_sys::Date::now()));
+                (Some(OutboxItem { claim, ..item }), Claim::Claimed)
+            }
+        };
+        let claimed = key.clone();
+        let callback = self
+            .link
+            .callback(move |claim| SyncMsg::Claimed(claimed.clone(), claim));
+        store::update_json(store::OUTBOX, &key, claim, callback);
+    }
+
+    fn send_first(&mut self) {
+        let server = host().unwrap();
+        let task = match self.queue.front().map(|item| &item.op) {
+            None => return,
+            // skip posting a state the server already has
+            Some(OutboxOp::Completed(payload)) => {
+                let query = format!("http://{}/get/completed/{}", server, payload.entry_id);
+                let request = Request::get(&query)
+                    .body(Nothing)
+                    .expect("Could not build request.");
+                let callback = self.link.callback(
+                    |response: Response<Json<Result<Vec<bool>, anyhow::Error>>>| {
+                        let status = response.status().as_u16();
+                        let Json(data) = response.into_body();
+                        SyncMsg::ReceiveCompleted(status, data)
+                    },
+                );
+                FetchService::fetch(request, callback)
+            }
+            Some(OutboxOp::Annotation { payload, .. }) => {
+                let query = format!("http://{}/get/annotations/{}", server, payload.entry_id);
+                let request = Request::get(&query)
+                    .body(Nothing)
+                    .expect("Could not build request.");
+                let callback = self.link.callback(
+                    |response: Response<Json<Result<Vec<Annotation>, anyhow::Error>>>| {
+                        let status = response.status().as_u16();
+                        let Json(data) = response.into_body();
+                        SyncMsg::ReceiveAnnotations(status, data)
+                    },
+                );
+                FetchService::fetch(request, callback)
+            }
+            Some(OutboxOp::Note(payload)) => {
+                let query = format!("http://{}/submit/note", server);
+                let body = json!({"pnContent": payload.note_content,
+                                  "pnTags": payload.tags});
+                self.post(query, body)
+            }
+            Some(OutboxOp::Tags(payload)) => {
+                let query = format!("http://{}/submit/tags", server);
+                let body = json!({"ptEntryID": payload.entry_id,
+                                  "ptTags": payload.tags});
+                self.post(query, body)
+            }
+            Some(OutboxOp::Untag(payload)) => {
+                let query = format!("http://{}/submit/untag", server);
+                let body = json!({"ptEntryID": payload.entry_id,
+                                  "ptTags": payload.tags});
+                self.post(query, body)
+            }
+            Some(OutboxOp::Edit(payload)) => {
+                let query = format!("http://{}/submit/edit", server);
+                let body = json!({"peEntryID": payload.entry_id,
+                                  "peContent": payload.content});
+                self.post(query, body)
+            }
+            Some(OutboxOp::Capture { note, .. }) => {
+                let query = format!("http://{}/submit/note", server);
+                let body = json!({"pnContent": note.note_content,
+                                  "pnTags": note.tags});
+                let request = Request::post(query)
+                    .header("Content-Type", "application/json")
+                    .body(Json(&body))
+                    .expect("Could not build request.");
+                let callback = self.link.callback(|response: Response<Text>| {
+                    let status = response.status().as_u16();
+                    let entry_id = response
+                        .into_body()
+                        .ok()
+                        .and_then(|body| body.trim().parse::<i32>().ok());
+                    SyncMsg::CaptureSent(status, entry_id)
+                });
+                FetchService::fetch(request, callback)
+            }
+        };
+        match task {
+            Ok(task) => self.task = Some(task),
+            Err(error) => log::info!("sync request failed to start: {:?}", error),
+        }
+    }
+
+    fn post(&self, query: String, body: serde_json::Value) -> Result<FetchTask, anyhow::Error> {
+        log::info!("sync posting to {}", query);
+        let request = Request::post(query)
+            .header("Content-Type", "application/json")
+            .body(Json(&body))
+            .expect("Could not build request.");
+        let callback = self
+            .link
+            .callback(|response: Response<Text>| SyncMsg::Sent(response.status().as_u16()));
+        FetchService::fetch(request, callback)
+    }
+
+    // the head of the queue reached the server, drop it and move on
+    fn complete_head(&mut self) {
+        if let Some(item) = self.queue.pop_front() {
+            store::delete(store::OUTBOX, &item.key);
+            self.notify(SyncStatus::Sent(item.key));
+        }
+        self.notify(SyncStatus::Pending(self.queue.len()));
+        self.flush();
+    }
+
+    // the head of the queue failed, keep it for the next try unless the
+    // server refused it, then it would block everything queued behind it
+    fn fail_head(&mut self, status: u16) {
+        if retryable(status) {
+            log::info!("sync failed with {}, retrying in {}s", status, RETRY_SECONDS);
+            return;
+        }
+        if let Some(item) = self.queue.pop_front() {
+            log::info!("sync rejected with {}, dropping {:?}", status, item.op);
+            store::delete(store::OUTBOX, &item.key);
+            self.notify(SyncStatus::Rejected(item, status));
+        }
+        self.notify(SyncStatus::Pending(self.queue.len()));
+        self.flush();
+    }
+
+    fn send_head(&mut self) {
+        let server = host().unwrap();
+        let task = match self.queue.front().map(|item| &item.op) {
+            Some(OutboxOp::Completed(payload)) => {
+                let query = format!("http://{}/submit/completed", server);
+                let body = json!({"pcEntryID": payload.entry_id,
+                                  "pcState": payload.state});
+                self.post(query, body)
+            }
+            Some(OutboxOp::Annotation { payload, .. }) => {
+                let query = format!("http://{}/submit/annotation", server);
+                let body = json!({"paEntryID": payload.entry_id,
+                                  "paContent": payload.content});
+                self.post(query, body)
+            }
+            _ => return,
+        };
+        match task {
+            Ok(task) => self.task = Some(task),
+            Err(error) => log::info!("sync request failed to start: {:?}", error),
+        }
+    }
+}
+
+impl Agent for SyncAgent {
+    type Reach = Context<Self>;
+    type Message = SyncMsg;
+    type Input = SyncRequest;
+    type Output = SyncStatus;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        store::get_all_json(store::OUTBOX, link.callback(SyncMsg::Loaded));
+        let interval = IntervalService::spawn(
+            Duration::from_secs(RETRY_SECONDS),
+            link.callback(|_| SyncMsg::Tick),
+        );
+        Self {
+            link,
+            subscribers: HashSet::new(),
+            queue: VecDeque::new(),
+            task: None,
+            loaded: false,
+            owner: format!("{}-{}", js_sys::Date::now(), js_sys::Math::random()),
+            claiming: false,
+            _interval: interval,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            SyncMsg::Loaded(items) => {
+                log::info!("outbox loaded with {} items", items.len());
+                // anything enqueued before the store answered was written after these
+                let keys: HashSet<String> = items.iter().map(|item| item.key.clone()).collect();
+                let enqueued: Vec<OutboxItem> = self
+                    .queue
+                    .drain(..)
+                    .filter(|item| !keys.contains(&item.key))
+                    .collect();
+                let mut queue: Vec<OutboxItem> = items.into_iter().chain(enqueued).collect();
+                queue.sort_by(|a, b| a.key.cmp(&b.key));
+                self.queue = queue.into();
+                self.loaded = true;
+                self.notify(SyncStatus::Pending(self.queue.len()));
+                self.flush();
+            }
+            SyncMsg::Tick => {
+                // pick up what other tabs queued, and left behind when closed
+                if self.task.is_none() && !self.claiming && self.loaded {
+                    self.loaded = false;
+                    store::get_all_json(store::OUTBOX, self.link.callback(SyncMsg::Loaded));
+                }
+            }
+            SyncMsg::Claimed(key, claim) => {
+                self.claiming = false;
+                if self.queue.front().map(|item| &item.key) != Some(&key) {
+                    self.flush();
+                    return;
+                }
+                match claim {
+                    Some(Claim::Claimed) | None => self.send_first(),
+                    Some(Claim::Taken) => log::info!("outbox item {} is being sent by another tab", key),
+                    Some(Claim::Gone) => {
+                        self.queue.pop_front();
+                        self.notify(SyncStatus::Sent(key));
+                        self.notify(SyncStatus::Pending(self.queue.len()));
+                        self.flush();
+                    }
+                }
+            }
+            SyncMsg::ReceiveCompleted(status, response) => {
+                self.task = None;
+                match (response, self.queue.front().map(|item| &item.op)) {
+                    (Ok(result), Some(OutboxOp::Completed(payload))) => {
+                        if result.first() == Some(&payload.state) {
+                            log::info!("completed state already on server for {}", payload.entry_id);
+                            self.complete_head();
+                        } else {
+                            self.send_head();
+                        }
+                    }
+                    (Err(error), _) => {
+                        log::info!("sync check failed: {}", error);
+                        self.fail_head(status);
+                    }
+                    _ => (),
+                }
+            }
+            SyncMsg::ReceiveAnnotations(status, response) => {
+                self.task = None;
+                match (response, self.queue.front().map(|item| &item.op)) {
+                    (Ok(result), Some(OutboxOp::Annotation { payload, base })) => {
+                        let server_content = result.last().map(|a| a.content.clone());
+                        if server_content.as_ref() == Some(&payload.content) {
+                            self.complete_head();
+                            return;
+                        }
+                        if server_content != *base {
+                            // annotations are append-only so both versions are kept,
+                            // the local edit just becomes the newest one
+                            log::info!("annotation conflict on entry {}", payload.entry_id);
+                            self.notify(SyncStatus::Conflict(payload.entry_id));
+                        }
+                        self.send_head();
+                    }
+                    (Err(error), _) => {
+                        log::info!("sync check failed: {}", error);
+                        self.fail_head(status);
+                    }
+                    _ => (),
+                }
+            }
+            SyncMsg::CaptureSent(status, _) if !(200..300).contains(&status) => {
+                self.task = None;
+                self.fail_head(status);
+            }
+            SyncMsg::CaptureSent(_, entry_id) => {
+                self.task = None;
+                match (entry_id, self.queue.pop_front()) {
+                    (Some(entry_id), Some(OutboxItem { key, op: OutboxOp::Capture { annotation, .. }, .. })) => {
+                        // the note exists now, what's left is an ordinary annotation
+                        let item = OutboxItem {
+                            key,
+                            op: OutboxOp::Annotation {
+                                payload: AnnotationPayload {
+                                    entry_id,
+                                    content: annotation,
+                                },
+                                base: None,
+                            },
+                            claim: None,
+                        };
+                        store::put_json(store::OUTBOX, &item.key, &item);
+                        self.queue.push_front(item);
+                        self.flush();
+                    }
+                    (_, item) => {
+                        // the note went through, don't post it twice over a missing id
+                        log::info!("no entry id for capture, dropping its annotation");
+                        if let Some(item) = item {
+                            store::delete(store::OUTBOX, &item.key);
+                            self.notify(SyncStatus::Sent(item.key));
+                        }
+                        self.notify(SyncStatus::Pending(self.queue.len()));
+                        self.flush();
+                    }
+                }
+            }
+            SyncMsg::Sent(status) => {
+                self.task = None;
+                if (200..300).contains(&status) {
+                    self.complete_head();
+                } else {
+                    self.fail_head(status);
+                }
+            }
+        }
+    }
+
+    fn connected(&mut self, id: HandlerId) {
+        self.subscribers.insert(id);
+        self.link.respond(id, SyncStatus::Pending(self.queue.len()));
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        match msg {
+            SyncRequest::Enqueue(op) => {
+                let item = OutboxItem {
+                    key: outbox_key(),
+                    op,
+                    claim: None,
+                };
+                store::put_json(store::OUTBOX, &item.key, &item);
+                self.link.respond(id, SyncStatus::Queued(item.key.clone()));
+                self.queue.push_back(item);
+                self.notify(SyncStatus::Pending(self.queue.len()));
+                self.flush();
+            }
+            SyncRequest::Flush => self.flush(),
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.remove(&id);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::api::*;
+use serde_json::json;
+use std::collections::BTreeSet;
+use urlencoding::encode;
+use yew::format::{Json, Nothing, Text};
+use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::utils::host;
+
+// Tag administration: rename, merge and delete tags across all entries,
+// with a preview of the entries a change touches before it is made.
+
+// entries listed in the preview, the count covers all of them
+const PREVIEW_SHOWN: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagOp {
+    // a single tag is a rename, several a merge
+    Rename { from: Vec<String>, to: String },
+    Delete(Vec<String>),
+}
+
+impl TagOp {
+    fn tags(&self) -> &[String] {
+        match self {
+            TagOp::Rename { from, .. } => from,
+            TagOp::Delete(tags) => tags,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            TagOp::Rename { from, to } if from.len() == 1 => format!("Rename {} to {}", from[0], to),
+            TagOp::Rename { from, to } => format!("Merge {} into {}", from.join(", "), to),
+            TagOp::Delete(tags) => format!("Remove {} from every entry", tags.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TagAdminMsg {
+    GetCounts,
+    ReceiveCounts(Result<Vec<TagCount>, anyhow::Error>),
+    Filter(String),
+    Toggle(String),
+    NewName(String),
+    Preview(TagOp),
+    ReceivePreview(Result<Vec<Cache>, anyhow::Error>),
+    Confirm,
+    Cancel,
+    Done(bool),
+}
+
+pub struct TagAdmin {
+    link: ComponentLink<Self>,
+    props: Props,
+    counts: Option<Vec<TagCount>>,
+    counts_task: Option<FetchTask>,
+    filter: String,
+    selected: BTreeSet<String>,
+    new_name: String,
+    // the change waiting for confirmation and the entries it touches
+    pending: Option<TagOp>,
+    preview: Vec<Cache>,
+    preview_tasks: Vec<FetchTask>,
+    preview_waiting: usize,
+    submit_task: Option<FetchTask>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    // tags were renamed, merged or deleted
+    #[prop_or_default]
+    pub tags_changed_callback: Callback<()>,
+}
+
+impl TagAdmin {
+    fn view_tags(&self) -> Html {
+        let counts = match &self.counts {
+            Some(counts) => counts,
+            None => return html! { <div>{"Loading..."}</div> },
+        };
+        let filter = self.filter.trim().to_lowercase();
+        html! {
+            <table class="tag-admin-table">
+                <tr><th></th><th>{"Tag"}</th><th>{"Entries"}</th></tr>
+                {
+                    for counts.iter().filter(|c| c.tag.to_lowercase().contains(&filter)).map(|c| {
+                        let tag = c.tag.clone();
+                        html! {
+                            <tr>
+                                <td>
+                                    <input type="checkbox" checked=self.selected.contains(&c.tag)
+                                        onclick=self.link.callback(move |_| TagAdminMsg::Toggle(tag.clone()))/>
+                                </td>
+                                <td>{ &c.tag }</td>
+                                <td class="tag-admin-count">{ c.count }</td>
+                            </tr>
+                        }
+                    })
+                }
+            </table>
+        }
+    }
+
+    fn view_actions(&self) -> Html {
+        if self.selected.is_empty() || self.pending.is_some() {
+            return html! {};
+        }
+        let from: Vec<String> = self.selected.iter().cloned().collect();
+        let to = self.new_name.trim().to_string();
+        let rename_label = if from.len() == 1 { "Rename" } else { "Merge into" };
+        let unusable = to.is_empty() || (from.len() == 1 && from[0] == to);
+        let delete = from.clone();
+        html! {
+            <div class="tag-admin-actions shadow-sm p-3 mb-3 bg-white rounded">
+                <span class="batch-count">{ format!("{} selected", from.len()) }</span>
+                <input type="text" class="batch-tag-input" placeholder="new name"
+                    value=self.new_name.clone()
+                    oninput=self.link.callback(|e: InputData| TagAdminMsg::NewName(e.value))/>
+                <button class="batch-button" disabled=unusable
+                    onclick=self.link.callback(move |_| TagAdminMsg::Preview(TagOp::Rename { from: from.clone(), to: to.clone() }))>
+                    { rename_label }
+                </button>
+                <button class="batch-button batch-delete"
+                    onclick=self.link.callback(move |_| TagAdminMsg::Preview(TagOp::Delete(delete.clone())))>
+                    {"Delete"}
+                </button>
+            </div>
+        }
+    }
+
+    fn view_preview(&self) -> Html {
+        let op = match &self.pending {
+            Some(op) => op,
+            None => return html! {},
+        };
+        let waiting = self.preview_waiting > 0 || self.submit_task.is_some();
+        let affected = if self.preview_waiting > 0 {
+            String::from("Finding affected entries...")
+        } else {
+            format!("{} entries affected", self.preview.len())
+        };
+        html! {
+            <div class="tag-admin-preview shadow-sm p-3 mb-3 bg-white rounded">
+                <div class="tag-admin-op">{ op.describe() }</div>
+                <font color="grey">{ affected }</font>
+                <ul>
+                    {
+                        for self.preview.iter().take(PREVIEW_SHOWN).map(|entry| html! {
+                            <li>{ entry.content.clone().or_else(|| entry.url.clone()).unwrap_or_default() }</li>
+                        })
+                    }
+                </ul>
+                <button class="batch-button" disabled=waiting onclick=self.link.callback(|_| TagAdminMsg::Confirm)>
+                    {"Confirm"}
+                </button>
+                <button class="batch-button" onclick=self.link.callback(|_| TagAdminMsg::Cancel)>
+                    {"Cancel"}
+                </button>
+            </div>
+        }
+    }
+}
+
+impl Component for TagAdmin {
+    type Message = TagAdminMsg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        link.send_message(TagAdminMsg::GetCounts);
+        Self {
+            link,
+            props,
+            counts: None,
+            counts_task: None,
+            filter: String::new(),
+            selected: BTreeSet::new(),
+            new_name: String::new(),
+            pending: None,
+            preview: vec![],
+            preview_tasks: vec![],
+            preview_waiting: 0,
+            submit_task: None,
+            error: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let server = host().unwrap();
+        match msg {
+            TagAdminMsg::GetCounts => {
+                let request = Request::get(&format!("http://{}/all/tags/counts", server))
+                    .body(Nothing)
+                    .expect("Could not build request.");
+                let callback = self.link.callback_once(
+                    |response: Response<Json<Result<Vec<TagCount>, anyhow::Error>>>| {
+                        let Json(data) = response.into_body();
+                        TagAdminMsg::ReceiveCounts(data)
+                    },
+                );
+                self.counts_task = FetchService::fetch(request, callback).ok();
+                false
+            }
+            TagAdminMsg::ReceiveCounts(response) => {
+                match response {
+                    Ok(counts) => {
+                        self.selected.retain(|tag| counts.iter().any(|c| c.tag == *tag));
+                        self.counts = Some(counts);
+                    }
+                    Err(error) => {
+                        log::info!("tag counts receive error: {}", error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+                self.counts_task = None;
+                true
+            }
+            TagAdminMsg::Filter(filter) => {
+                self.filter = filter;
+                true
+            }
+            TagAdminMsg::Toggle(tag) => {
+                if !self.selected.remove(&tag) {
+                    // merging defaults to the first tag picked
+                    if self.selected.is_empty() {
+                        self.new_name = tag.clone();
+                    }
+                    self.selected.insert(tag);
+                }
+                true
+            }
+            TagAdminMsg::NewName(name) => {
+                self.new_name = name;
+                true
+            }
+            TagAdminMsg::Preview(op) => {
+                // one listing per tag, the union is what the change touches
+                self.preview = vec![];
+                self.preview_tasks = op
+                    .tags()
+                    .iter()
+                    .filter_map(|tag| {
+                        let request = Request::get(&format!("http://{}/all/cache?limit=100000&tag={}", server, encode(tag)))
+                            .body(Nothing)
+                            .expect("Could not build request.");
+                        let callback = self.link.callback_once(
+                            |response: Response<Json<Result<Vec<Cache>, anyhow::Error>>>| {
+                                let Json(data) = response.into_body();
+                                TagAdminMsg::ReceivePreview(data)
+                            },
+                        );
+                        FetchService::fetch(request, callback).ok()
+                    })
+                    .collect();
+                self.preview_waiting = self.preview_tasks.len();
+                self.pending = Some(op);
+                true
+            }
+            TagAdminMsg::ReceivePreview(response) => {
+                self.preview_waiting = self.preview_waiting.saturating_sub(1);
+                match response {
+                    Ok(entries) => {
+                        for entry in entries {
+                            if !self.preview.iter().any(|e| e.entry_id == entry.entry_id) {
+                                self.preview.push(entry);
+                            }
+                        }
+                    }
+                    Err(error) => self.error = Some(error.to_string()),
+                }
+                true
+            }
+            TagAdminMsg::Confirm => {
+                let (path, body) = match &self.pending {
+                    Some(TagOp::Rename { from, to }) => ("rename", json!({"trFrom": from, "trTo": to})),
+                    Some(TagOp::Delete(tags)) => ("delete", json!({"tdTags": tags})),
+                    None => return false,
+                };
+                let request = Request::post(format!("http://{}/submit/tags/{}", server, path))
+                    .header("Content-Type", "application/json")
+                    .body(Json(&body))
+                    .expect("Could not build request.");
+                let callback = self
+                    .link
+                    .callback_once(|response: Response<Text>| TagAdminMsg::Done(response.status().is_success()));
+                self.submit_task = FetchService::fetch(request, callback).ok();
+                true
+            }
+            TagAdminMsg::Cancel => {
+                self.pending = None;
+                self.preview = vec![];
+                self.preview_tasks = vec![];
+                self.preview_waiting = 0;
+                true
+            }
+            TagAdminMsg::Done(success) => {
+                self.submit_task = None;
+                if success {
+                    self.error = None;
+                    self.pending = None;
+                    self.preview = vec![];
+                    self.selected.clear();
+                    self.new_name = String::new();
+                    self.link.send_message(TagAdminMsg::GetCounts);
+                    self.props.tags_changed_callback.emit(());
+                } else {
+                    self.error = Some(String::from("The server could not change the tags"));
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="tag-admin">
+                <h4>{"Tags"}</h4>
+                {
+                    match &self.error {
+                        Some(error) => html! { <div class="search-error">{ error }</div> },
+                        None => html! {},
+                    }
+                }
+                { self.view_preview() }
+                { self.view_actions() }
+                <input type="text" class="batch-tag-input" placeholder="filter tags"
+                    value=self.filter.clone()
+                    oninput=self.link.callback(|e: InputData| TagAdminMsg::Filter(e.value))/>
+                { self.view_tags() }
+            </div>
+        }
+    }
+}
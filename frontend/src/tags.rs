@@ -1,29 +1,317 @@
+use crate::api::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use yew::format::Json;
 use yew::prelude::*;
+use yew::services::storage::{Area, StorageService};
 use yew::Properties;
 
+// ordering and layout are kept between visits
+const TAG_VIEW_KEY: &str = "openmemex.tag_view";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TagOrder {
+    Alphabetical,
+    Count,
+    Recent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TagLayout {
+    List,
+    // size weighted by count
+    Cloud,
+    // namespaces like ml/transformers or project:openmemex as a collapsible tree
+    Tree,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagView {
+    pub order: TagOrder,
+    pub layout: TagLayout,
+}
+
+impl Default for TagView {
+    fn default() -> Self {
+        TagView {
+            order: TagOrder::Alphabetical,
+            layout: TagLayout::Tree,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TagNode {
+    // the tag up to and including this level, e.g. "project:openmemex"
+    pub path: String,
+    pub label: String,
+    // entries tagged with exactly this path
+    pub count: usize,
+    // count summed over the whole subtree
+    pub total: usize,
+    pub latest: Option<String>,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    // what selecting the node filters on, parents take their whole subtree
+    pub fn query_tag(&self) -> String {
+        if self.children.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}/*", self.path)
+        }
+    }
+}
+
+// (end offset, label) of each level, "project:openmemex/x" -> [(7, "project"), (17, "openmemex"), (19, "x")]
+fn tag_levels(tag: &str) -> Vec<(usize, &str)> {
+    let mut levels = vec![];
+    let mut start = 0;
+    for (i, c) in tag.char_indices() {
+        if c == '/' || c == ':' {
+            // empty segments (urls, doubled separators) aren't levels
+            if i > start {
+                levels.push((i, &tag[start..i]));
+            }
+            start = i + 1;
+        }
+    }
+    if start < tag.len() {
+        levels.push((tag.len(), &tag[start..]));
+    } else if let Some(last) = levels.last_mut() {
+        // a trailing separator stays part of the tag
+        last.0 = tag.len();
+    }
+    levels
+}
+
+fn insert_tag(nodes: &mut Vec<TagNode>, tag: &TagCount, levels: &[(usize, &str)]) {
+    let (end, label) = levels[0];
+    let path = &tag.tag[..end];
+    let index = match nodes.iter().position(|node| node.path == path) {
+        Some(index) => index,
+        None => {
+            nodes.push(TagNode {
+                path: path.to_string(),
+                label: label.to_string(),
+                count: 0,
+                total: 0,
+                latest: None,
+                children: vec![],
+            });
+            nodes.len() - 1
+        }
+    };
+    let node = &mut nodes[index];
+    node.total += tag.count;
+    if tag.latest > node.latest {
+        node.latest = tag.latest.clone();
+    }
+    if levels.len() == 1 {
+        node.count += tag.count;
+    } else {
+        insert_tag(&mut node.children, tag, &levels[1..]);
+    }
+}
+
+pub fn tag_tree(tags: &[TagCount]) -> Vec<TagNode> {
+    let mut roots = vec![];
+    for tag in tags {
+        let levels = tag_levels(&tag.tag);
+        if !levels.is_empty() {
+            insert_tag(&mut roots, tag, &levels);
+        }
+    }
+    roots
+}
+
+fn sort_tree(nodes: &mut [TagNode], order: TagOrder) {
+    match order {
+        TagOrder::Alphabetical => nodes.sort_by(|a, b| a.label.cmp(&b.label)),
+        TagOrder::Count => nodes.sort_by(|a, b| b.total.cmp(&a.total).then(a.label.cmp(&b.label))),
+        TagOrder::Recent => nodes.sort_by(|a, b| b.latest.cmp(&a.latest).then(a.label.cmp(&b.label))),
+    }
+    for node in nodes.iter_mut() {
+        sort_tree(&mut node.children, order);
+    }
+}
+
+// drops nodes that neither match nor have a matching descendant
+fn filter_tree(nodes: Vec<TagNode>, filter: &str) -> Vec<TagNode> {
+    nodes
+        .into_iter()
+        .filter_map(|mut node| {
+            if node.path.to_lowercase().contains(filter) {
+                return Some(node);
+            }
+            node.children = filter_tree(node.children, filter);
+            if node.children.is_empty() {
+                None
+            } else {
+                Some(node)
+            }
+        })
+        .collect()
+}
+
 pub enum TagsMsg {
     TagClick(MouseEvent, String),
     TagHover(MouseEvent, String),
     TagExit(MouseEvent, String),
+    Filter(String),
+    Order(ChangeData),
+    Layout(ChangeData),
+    Expand(String),
+    Min(String),
 }
 
-#[derive(Debug)]
 pub struct Tags {
     pub link: ComponentLink<Self>,
-    tags: Option<Vec<String>>,
+    tags: Option<Vec<TagCount>>,
     pub tag_click_callback: Callback<Option<String>>,
     pub selected: Option<HashSet<String>>,
     pub hovered: Option<String>,
+    min: usize,
+    min_change_callback: Callback<usize>,
+    filter: String,
+    view: TagView,
+    // tree nodes opened by the user
+    expanded: HashSet<String>,
+    storage: Option<StorageService>,
 }
 
 #[derive(Properties, PartialEq, Clone)]
 pub struct Props {
-    pub tags: Option<Vec<String>>,
+    pub tags: Option<Vec<TagCount>>,
     pub tag_click_callback: Callback<Option<String>>,
+    // fewest entries a tag needs to be listed
+    pub min: usize,
+    pub min_change_callback: Callback<usize>,
+}
+
+// font size between 0.8em and 2em, on a log scale so a few huge tags don't flatten the rest
+fn cloud_size(count: usize, max: usize) -> String {
+    let weight = if max > 1 {
+        (count.max(1) as f64).ln() / (max as f64).ln()
+    } else {
+        0.0
+    };
+    format!("font-size: {:.2}em", 0.8 + 1.2 * weight)
 }
 
-impl Tags {}
+impl Tags {
+    fn save_view(&mut self) {
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(TAG_VIEW_KEY, Json(&self.view));
+        }
+    }
+
+    // tags passing the filter box, in the chosen order
+    fn shown(&self) -> Vec<&TagCount> {
+        let filter = self.filter.trim().to_lowercase();
+        let mut shown: Vec<&TagCount> = self
+            .tags
+            .iter()
+            .flatten()
+            .filter(|t| t.tag.to_lowercase().contains(&filter))
+            .collect();
+        match self.view.order {
+            TagOrder::Alphabetical => shown.sort_by(|a, b| a.tag.cmp(&b.tag)),
+            TagOrder::Count => shown.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(&b.tag))),
+            TagOrder::Recent => shown.sort_by(|a, b| b.latest.cmp(&a.latest).then(a.tag.cmp(&b.tag))),
+        }
+        shown
+    }
+
+    // one clickable tag, `key` is what a click filters the gallery on
+    fn view_tag(&self, key: String, label: &str, count: usize, title: String, size: String) -> Html {
+        let hs = self.selected.clone().unwrap_or_default();
+        let mut style = "topic-tag";
+        if self.hovered.as_ref() == Some(&key) {
+            style = "topic-tag-hover";
+        }
+        if hs.contains(&key) {
+            style = "topic-tag-selected";
+        }
+        let click = key.clone();
+        let hover = key.clone();
+        html! {
+            <div class= { style } style=size title=title
+                onclick=self.link.callback(move |m| TagsMsg::TagClick(m, click.clone()))
+                onmouseover=self.link.callback(move |m| TagsMsg::TagHover(m, hover.clone()))
+                onmouseleave=self.link.callback(move |m| TagsMsg::TagExit(m, key.clone()))>
+            { label }
+            <span class="tag-count">{ count }</span>
+            </div>
+        }
+    }
+
+    // a tree node and, when open, its children indented below it
+    fn view_node(&self, node: &TagNode, depth: usize, open_all: bool) -> Html {
+        let open = open_all || self.expanded.contains(&node.path);
+        let toggle = if node.children.is_empty() {
+            html! { <span class="tag-tree-toggle"></span> }
+        } else {
+            let path = node.path.clone();
+            html! {
+                <span class="tag-tree-toggle" onclick=self.link.callback(move |_| TagsMsg::Expand(path.clone()))>
+                    { if open { "▾" } else { "▸" } }
+                </span>
+            }
+        };
+        let title = if node.children.is_empty() {
+            node.path.clone()
+        } else {
+            format!("{} and everything under it, {} tagged {} itself", node.path, node.count, node.path)
+        };
+        html! {
+            <>
+                <div class="tag-tree-node" style=format!("padding-left: {}em", depth)>
+                    { toggle }
+                    { self.view_tag(node.query_tag(), &node.label, node.total, title, String::new()) }
+                </div>
+                {
+                    if open {
+                        html! { for node.children.iter().map(|child| self.view_node(child, depth + 1, open_all)) }
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
+
+    fn view_controls(&self) -> Html {
+        let order = self.view.order;
+        let layout = self.view.layout;
+        html! {
+            <div class="tags-controls">
+                <input type="text" class="tags-filter" placeholder="filter tags"
+                    value=self.filter.clone()
+                    oninput=self.link.callback(|e: InputData| TagsMsg::Filter(e.value))/>
+                <select class="tags-order" onchange=self.link.callback(TagsMsg::Order)>
+                    <option value="alphabetical" selected=order == TagOrder::Alphabetical>{"A-Z"}</option>
+                    <option value="count" selected=order == TagOrder::Count>{"Most used"}</option>
+                    <option value="recent" selected=order == TagOrder::Recent>{"Recent"}</option>
+                </select>
+                <select class="tags-layout" onchange=self.link.callback(TagsMsg::Layout)>
+                    <option value="tree" selected=layout == TagLayout::Tree>{"Tree"}</option>
+                    <option value="list" selected=layout == TagLayout::List>{"List"}</option>
+                    <option value="cloud" selected=layout == TagLayout::Cloud>{"Cloud"}</option>
+                </select>
+                <label class="tags-min" title="fewest entries a tag needs to be shown">
+                    {"min "}
+                    <input type="number" min="1" value=self.min.to_string()
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Value(value) => TagsMsg::Min(value),
+                            _ => TagsMsg::Min(String::new()),
+                        })/>
+                </label>
+            </div>
+        }
+    }
+}
 
 impl Component for Tags {
     type Message = TagsMsg;
@@ -31,18 +319,34 @@ impl Component for Tags {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         log::info!("Creating tags component");
+        let storage = StorageService::new(Area::Local).ok();
+        let view = match &storage {
+            Some(storage) => {
+                let Json(view) = storage.restore(TAG_VIEW_KEY);
+                view.unwrap_or_default()
+            }
+            None => TagView::default(),
+        };
         Self {
             link: link,
             tags: props.tags,
             tag_click_callback: props.tag_click_callback,
             selected: None,
             hovered: None,
+            min: props.min,
+            min_change_callback: props.min_change_callback,
+            filter: String::new(),
+            view,
+            expanded: HashSet::new(),
+            storage,
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.tags = props.tags;
         self.tag_click_callback = props.tag_click_callback;
+        self.min = props.min;
+        self.min_change_callback = props.min_change_callback;
         true
     }
 
@@ -68,54 +372,87 @@ impl Component for Tags {
                 self.hovered = Some(tag_name);
                 true
             }
-            TagExit(_m, tag_name) => {
+            TagExit(_m, _tag_name) => {
                 log::info!("tag exit event");
                 self.hovered = None;
                 true
             }
+            Filter(filter) => {
+                self.filter = filter;
+                true
+            }
+            Order(ChangeData::Select(select)) => {
+                self.view.order = match select.value().as_str() {
+                    "count" => TagOrder::Count,
+                    "recent" => TagOrder::Recent,
+                    _ => TagOrder::Alphabetical,
+                };
+                self.save_view();
+                true
+            }
+            Order(_) => false,
+            Layout(ChangeData::Select(select)) => {
+                self.view.layout = match select.value().as_str() {
+                    "list" => TagLayout::List,
+                    "cloud" => TagLayout::Cloud,
+                    _ => TagLayout::Tree,
+                };
+                self.save_view();
+                true
+            }
+            Layout(_) => false,
+            Expand(path) => {
+                if !self.expanded.remove(&path) {
+                    self.expanded.insert(path);
+                }
+                true
+            }
+            Min(value) => {
+                match value.trim().parse::<usize>() {
+                    Ok(min) if min >= 1 && min != self.min => self.min_change_callback.emit(min),
+                    _ => (),
+                }
+                false
+            }
         }
     }
 
     fn view(&self) -> Html {
-        let empty_vec = &[].to_vec();
-        let exist_tags = self.tags.as_ref().unwrap_or(empty_vec);
-        let callback = |item: String| {
-            self.link
-                .callback(move |m| TagsMsg::TagClick(m, item.to_string()))
-        };
-        let hover_callback = |item: String| {
-            self.link
-                .callback(move |m| TagsMsg::TagHover(m, item.to_string()))
-        };
-        let exit_callback = |item: String| {
-            self.link
-                .callback(move |m| TagsMsg::TagExit(m, item.to_string()))
+        let body = match self.view.layout {
+            TagLayout::Tree => {
+                let filter = self.filter.trim().to_lowercase();
+                let mut tree = tag_tree(self.tags.as_deref().unwrap_or(&[]));
+                if !filter.is_empty() {
+                    tree = filter_tree(tree, &filter);
+                }
+                sort_tree(&mut tree, self.view.order);
+                html! {
+                    <div class="tag-tree">
+                        { for tree.iter().map(|node| self.view_node(node, 0, !filter.is_empty())) }
+                    </div>
+                }
+            }
+            layout => {
+                let shown = self.shown();
+                let max = shown.iter().map(|t| t.count).max().unwrap_or(1);
+                let cloud = layout == TagLayout::Cloud;
+                html! {
+                    <div class=if cloud { "tag-cloud" } else { "" }>
+                        { for shown.into_iter().map(|item: &TagCount| {
+                            let size = if cloud { cloud_size(item.count, max) } else { String::new() };
+                            let title = item.latest.clone().map(|latest| format!("last used {}", latest)).unwrap_or_default();
+                            self.view_tag(item.tag.clone(), &item.tag, item.count, title, size)
+                         })
+                        }
+                    </div>
+                }
+            }
         };
 
-        let hovered = self.hovered.clone().unwrap_or("".to_string());
-
         html! {
                 <div class="topic-tags">
-                    <div>
-                        { for exist_tags.iter().map((move |item: &String| {
-                            let hs = self.selected.clone().unwrap_or(HashSet::new());
-                            let mut style = "topic-tag";
-                            if hovered.eq(item) {
-                                style = "topic-tag-hover";
-                            }
-                            if hs.contains(item) {
-                                style = "topic-tag-selected";
-                            }
-                            html! {
-                                <div class= { style } onclick=callback(item.clone()).clone() 
-                                    onmouseover=hover_callback(item.clone()).clone()
-                                    onmouseleave=exit_callback(item.clone()).clone()>
-                                { item.clone() }
-                                </div>
-                            }
-                         }).clone() )
-                        }
-                    </div>
+                    { self.view_controls() }
+                    { body }
             </div>
         }
     }
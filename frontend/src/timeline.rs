@@ -1,4 +1,5 @@
 use crate::api::*;
+use crate::store;
 use yew::Properties;
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::{
@@ -14,6 +15,7 @@ use wasm_bindgen::prelude::*;
 pub enum TimelineMsg {
     GetTimeline,
     ReceiveTimeline(Result<Vec<Timestamp>, anyhow::Error>),
+    ReceiveCachedTimeline(Option<Vec<Timestamp>>),
     Hover(MouseEvent, String),
     Click(MouseEvent),
 }
@@ -28,6 +30,7 @@ pub struct Timeline {
     pub utc_range: (i64, i64),
     pub timeline_callback: Callback<Option<(NaiveDateTime, NaiveDateTime)>>,
     task: Option<FetchTask>,
+    fresh: bool,
 }
 
 const window_half_duration: i64 = 3;
@@ -60,6 +63,25 @@ fn get_time(&mut self) -> (NaiveDateTime, NaiveDateTime) {
 }
 */
 
+impl Timeline {
+    fn set_events(&mut self, result: Vec<Timestamp>) {
+        self.events = result.clone();
+        let mut timestamps = vec![0; result.len()]; // Vec::with_capacity(result.len());
+        for (i, timestamp) in result.iter().enumerate() {
+            timestamps[i] = timestamp.utc
+        }
+        let min = (*timestamps.iter().min().unwrap_or(&0)) as i64;
+        let max = (*timestamps.iter().max().unwrap_or(&0)) as i64;
+        let minf = min as f32;
+        let maxf = max as f32;
+        self.locations = timestamps
+            .into_iter()
+            .map(|x| 100.0 * (((x as f32) - minf) / (maxf - minf)))
+            .collect();
+        self.utc_range = (min, max);
+    }
+}
+
 impl Component for Timeline {
     type Message = TimelineMsg;
     type Properties = Props;
@@ -78,6 +100,7 @@ impl Component for Timeline {
             utc_range: (0, 0),
             timeline_callback: props.timeline_callback,
             task: None,
+            fresh: false,
         }
     }
 
@@ -92,6 +115,7 @@ impl Component for Timeline {
         match msg {
             GetTimeline => {
                 let query = format!("http://{}/all/timestamps/", server.to_string());
+                store::get_json(store::TIMESTAMPS, &query, self.link.callback(ReceiveCachedTimeline));
                 log::info!("submitting tl request: {}", query);
                 let request = Request::get(&query)
                     .body(Nothing)
@@ -106,24 +130,21 @@ impl Component for Timeline {
                 log::info!("submitting timeline request");
                 false
             }
+            ReceiveCachedTimeline(cached) => match cached {
+                Some(result) if !self.fresh => {
+                    self.set_events(result);
+                    true
+                }
+                _ => false,
+            },
             ReceiveTimeline(response) => {
                 match response {
                     Ok(result) => {
                         log::info!("timeline results {}", result.len());
-                        self.events = result.clone();
-                        let mut timestamps = vec![0; result.len()]; // Vec::with_capacity(result.len());
-                        for (i, timestamp) in result.iter().enumerate() {
-                            timestamps[i] = timestamp.utc
-                        }
-                        let min = (*timestamps.iter().min().unwrap_or(&0)) as i64;
-                        let max = (*timestamps.iter().max().unwrap_or(&0)) as i64;
-                        let minf = min as f32;
-                        let maxf = max as f32;
-                        self.locations = timestamps
-                            .into_iter()
-                            .map(|x| 100.0 * (((x as f32) - minf) / (maxf - minf)))
-                            .collect();
-                        self.utc_range = (min, max);
+                        let query = format!("http://{}/all/timestamps/", server);
+                        store::put_json(store::TIMESTAMPS, &query, &result);
+                        self.fresh = true;
+                        self.set_events(result);
                     }
                     Err(error) => {
                         log::info!("timeline error:");
@@ -0,0 +1,77 @@
+// Service worker for the installable frontend.
+//
+// The app shell (index.html, the wasm bundle and static assets) is cached so
+// the UI starts without a server. API requests are not intercepted, the wasm
+// side keeps its own IndexedDB cache of server data.
+
+const CACHE_NAME = "openmemex-shell-v1";
+
+const SHELL = [
+  "index.html",
+  "wasm.js",
+  "wasm_bg.wasm",
+  "style.css",
+  "manifest.json",
+  "icon.svg",
+];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches.open(CACHE_NAME).then((cache) => cache.addAll(SHELL))
+  );
+  self.skipWaiting();
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches.keys().then((keys) =>
+      Promise.all(
+        keys.filter((key) => key !== CACHE_NAME).map((key) => caches.delete(key))
+      )
+    )
+  );
+  self.clients.claim();
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  const url = new URL(request.url);
+  if (url.origin !== self.location.origin || !url.pathname.startsWith("/frontend/")) {
+    return;
+  }
+
+  // Client-side routes such as /frontend/addnote (the share target) have no
+  // file on the server, so every navigation gets the app shell and the router
+  // takes it from there. The network copy wins when the server is up.
+  if (request.mode === "navigate") {
+    event.respondWith(
+      fetch("index.html")
+        .then((response) => {
+          const copy = response.clone();
+          caches.open(CACHE_NAME).then((cache) => cache.put("index.html", copy));
+          return response;
+        })
+        .catch(() => caches.match("index.html"))
+    );
+    return;
+  }
+
+  // Static assets: serve from cache, refresh it in the background.
+  event.respondWith(
+    caches.match(request).then((cached) => {
+      const network = fetch(request)
+        .then((response) => {
+          if (response.ok) {
+            const copy = response.clone();
+            caches.open(CACHE_NAME).then((cache) => cache.put(request, copy));
+          }
+          return response;
+        })
+        .catch(() => cached);
+      return cached || network;
+    })
+  );
+});
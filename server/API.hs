@@ -5,6 +5,7 @@ module API where
 import Control.Monad.IO.Class (liftIO)
 import CrawlTools
 import Data.Int (Int64)
+import Data.List (isPrefixOf)
 import Date
 import Data.Time ( Day(..), TimeOfDay(..), UTCTime(..))
 import DB
@@ -12,6 +13,8 @@ import Servant
 import Data.Text (Text, pack, unpack)
 import Data.Aeson (FromJSON, ToJSON)
 import GHC.Generics (Generic)
+import Network.HTTP.Types (hContentType, status200)
+import Network.Wai (responseFile)
 
 
 data PostSearch =
@@ -32,8 +35,14 @@ allTimestampsH = liftIO allTimeStamps
 -- | Static file serving endpoint
 frontendH = serveDirectoryFileServer "./static/."
 -- frontendH = serveDirectoryWebApp "./static/"
+
+-- | Client side routes (e.g. the bookmarklet capture page) have no file
+-- under ./static, serve the app shell and let the frontend router take over
+appShellH :: Server Raw
+appShellH = Tagged $ \_ respond ->
+  respond $ responseFile status200 [(hContentType, "text/html")] "./static/index.html" Nothing
  
-linkEntryTagsH filterTag = liftIO $ linkEntryTags filterTag
+linkEntryTagsH filterTag entryIDs = liftIO $ linkEntryTags filterTag entryIDs
 
 -- | Post a note 
 postNoteH note = liftIO $ postNote note
@@ -41,10 +50,67 @@ postNoteH note = liftIO $ postNote note
 -- | Alter state for content being completed
 postCompletedH entryID = liftIO $ postCompleted entryID
 
+-- | Add tags to an existing entry
+postTagsH tags = liftIO $ postTags tags
+
+-- | Remove tags from an entry
+postUntagH tags = liftIO $ postUntag tags
+
+-- | Change the content of an entry
+postEditH edit = liftIO $ postEdit edit
+
+-- | Retrieve an entry with its cached page details, for opening Detail by id
+getEntryH :: Int -> Handler [CacheView]
+getEntryH entryID = liftIO $ cacheView entryID
+
+-- | Retrieve the tags of an entry
+getTagsH :: Int -> Handler [String]
+getTagsH entryID = liftIO $ getTags entryID
+
+-- | Crawl an entry's page again
+postRecrawlH recrawl = liftIO $ postRecrawl recrawl
+
+-- | Soft delete an entry, it can be restored from the archive
+archiveEntryH :: Int -> Handler Int64
+archiveEntryH entryID = liftIO $ archiveEntryById entryID
+
+-- | Bring an archived entry back to the gallery
+restoreEntryH :: Int -> Handler Int64
+restoreEntryH entryID = liftIO $ restoreEntryById entryID
+
+-- | Permanently delete an entry and its cached files
+purgeEntryH :: Int -> Handler Int64
+purgeEntryH entryID = liftIO $ purgeEntryById entryID
+
+-- | Retrieve archived entries
+allArchivedH :: Handler [CacheView]
+allArchivedH = liftIO archivedCache
+
+-- | Add an annotation (detail note) to an entry
+postAnnotationH annotation = liftIO $ postAnnotation annotation
+
+-- | Retrieve the annotation history of an entry
+getAnnotationsH :: Int -> Handler [Annotation]
+getAnnotationsH entryID = liftIO $ getAnnotations entryID
+
+-- | Annotation counts of all entries, for the gallery note cards
+annotationCountsH :: Handler [AnnotationCount]
+annotationCountsH = liftIO annotationCounts
+
 -- | Retrieve a list of all topic tags
 allTagsH :: Maybe Int -> Handler [String]
 allTagsH minCount = liftIO $ allTags minCount
 
+-- | Retrieve every tag with its usage count
+allTagCountsH :: Maybe Int -> Handler [TagCount]
+allTagCountsH minCount = liftIO $ tagCounts minCount
+
+-- | Rename one tag, or merge several into one
+postTagRenameH rename = liftIO $ postTagRename rename
+
+-- | Remove tags from all entries
+postTagDeleteH tags = liftIO $ postTagDelete tags
+
 -- | Retrieve all entries
 allEntriesH :: Handler [Entry]
 allEntriesH = liftIO allEntries
@@ -57,6 +123,11 @@ allCacheH
   -> Maybe Bool 
   -> Maybe Day 
   -> Maybe Day 
+  -> [Text] -- ^ excludeTags
+  -> Maybe Text -- ^ site
+  -> Maybe Bool -- ^ completed
+  -> Maybe Text -- ^ kind
+  -> Maybe Bool -- ^ alltags
   -> Handler [CacheView]
 allCacheH 
   sortby 
@@ -66,14 +137,44 @@ allCacheH
   hideCompleted 
   startDate
   endDate
-  = liftIO (allCache sortby sortdir filterTags limit hideCompleted startDate endDate)
+  excludeTags
+  site
+  completed
+  kind
+  allTags
+  = liftIO (allCache sortby sortdir entryFilter limit hideCompleted)
+  where
+    entryFilter = EntryFilter filterTags (allTags == Just True) excludeTags site completed kind startDate endDate
     
 -- | Retrieve state for content being completed
 getCompletedH entryID = liftIO $ getCompleted entryID
 
+-- | Ids of all completed entries, for the gallery table
+allCompletedH :: Handler [Int]
+allCompletedH = liftIO completedEntries
+
 -- | Searchbox retrieval
 searchH query = liftIO $ search query
 
+-- | Ranked search with the matching field and a snippet per entry
+searchResultsH query limit filterTags excludeTags site completed kind startDate endDate allTags =
+  liftIO $ searchResults (maybe "" id query) entryFilter limit
+  where
+    entryFilter = EntryFilter filterTags (allTags == Just True) excludeTags site completed kind startDate endDate
+
+-- | Entries captured from a url, for duplicate checks before capturing it again
+duplicatesH :: Maybe String -> Handler [CacheView]
+duplicatesH url = liftIO $ maybe (pure []) urlMatches url
+
+-- | Number of entries a gallery query matches, for the collection badges
+countH query filterTags excludeTags site completed kind startDate endDate allTags =
+  liftIO $ entryCount (maybe "" id query) entryFilter
+  where
+    entryFilter = EntryFilter filterTags (allTags == Just True) excludeTags site completed kind startDate endDate
+
+-- | Entries related to one, for the detail sidebar
+relatedH entryID limit = liftIO $ relatedEntries entryID limit
+
 {- Implementations (any DB queries are in DB.hs) -}
 
 -- | Add a note
@@ -105,3 +206,70 @@ postCompleted (PostCompleted entryID state) = do
     False -> removeCompleted entryID
   
   pure 0
+
+-- | Persist an annotation, annotations are append-only so older versions are kept
+postAnnotation :: PostAnnotation -> IO Int64
+postAnnotation (PostAnnotation entryID content) = do
+  putStrLn $ "Adding annotation to " ++ show entryID
+  addAnnotation entryID content
+
+-- | Tag an entry that already exists (e.g. a duplicate capture)
+postTags :: PostTags -> IO Int64
+postTags (PostTags entryID tags) = do
+  putStrLn $ "Adding tags " ++ show tags ++ " to " ++ show entryID
+  addTags entryID tags
+  pure 0
+
+-- | Untag an entry (batch action on cards)
+postUntag :: PostTags -> IO Int64
+postUntag (PostTags entryID tags) = do
+  putStrLn $ "Removing tags " ++ show tags ++ " from " ++ show entryID
+  removeTags entryID tags
+  pure 0
+
+postTagRename :: PostTagRename -> IO Int64
+postTagRename (PostTagRename fromTags toTag) = do
+  putStrLn $ "Renaming tags " ++ show fromTags ++ " to " ++ show toTag
+  mergeTags fromTags toTag
+  pure 0
+
+postTagDelete :: PostTagDelete -> IO Int64
+postTagDelete (PostTagDelete tags) = do
+  putStrLn $ "Deleting tags " ++ show tags
+  deleteTags tags
+  pure 0
+
+-- | Edit an entry's content, a changed link is crawled again
+postEdit :: PostEdit -> IO Int64
+postEdit (PostEdit entryID newContent) = do
+  putStrLn $ "Editing entry " ++ show entryID
+  old <- getEntry entryID
+  updateContent entryID newContent
+  case old of
+    (entry : _) | content entry /= newContent && "http" `isPrefixOf` newContent ->
+      postRecrawl (PostRecrawl entryID)
+    _ -> pure 0
+
+-- | Replace an entry's cached page, screenshot and thumbnail
+postRecrawl :: PostRecrawl -> IO Int64
+postRecrawl (PostRecrawl entryID) = do
+  putStrLn $ "Recrawling " ++ show entryID
+  removeCache entryID
+  entry <- getEntry entryID
+  crawlEntries entry
+  pure 0
+
+archiveEntryById :: Int -> IO Int64
+archiveEntryById entryID = do
+  putStrLn $ "Archiving entry " ++ show entryID
+  archiveEntry entryID
+
+restoreEntryById :: Int -> IO Int64
+restoreEntryById entryID = do
+  putStrLn $ "Restoring entry " ++ show entryID
+  restoreEntry entryID
+
+purgeEntryById :: Int -> IO Int64
+purgeEntryById entryID = do
+  putStrLn $ "Purging entry " ++ show entryID
+  purgeEntry entryID
@@ -35,6 +35,12 @@ type RootAPI = Get '[JSON] [String]
 
 type AllTagsAPI = "all" :> "tags" :> QueryParam "min" Int :> Get '[JSON] [String]
 
+type AllTagCountsAPI = "all" :> "tags" :> "counts" :> QueryParam "min" Int :> Get '[JSON] [TagCount]
+
+type TagRenameAPI = "submit" :> "tags" :> "rename" :> ReqBody '[JSON] PostTagRename :> Post '[JSON] Int64
+
+type TagDeleteAPI = "submit" :> "tags" :> "delete" :> ReqBody '[JSON] PostTagDelete :> Post '[JSON] Int64
+
 type AllEntriesAPI = "all" :> "entries" :> Get '[JSON] [Entry]
 
 type AllTimestampsAPI = "all" :> "timestamps" :> Get '[JSON] [DateTime]
@@ -49,6 +55,11 @@ type AllCacheAPI =
     :> QueryParam "hidecompleted" Bool
     :> QueryParam "startDate" Day
     :> QueryParam "endDate" Day
+    :> QueryParams "excludeTag" Text
+    :> QueryParam "site" Text
+    :> QueryParam "completed" Bool
+    :> QueryParam "kind" Text
+    :> QueryParam "alltags" Bool
     :> Get '[JSON] [CacheView]
 
 type ContentAPI = "content" :> Capture "query" String :> Get '[JSON] [CacheView]
@@ -58,9 +69,76 @@ type EntryAPI = "submit" :> "note" :> ReqBody '[JSON] PostNote :> Post '[JSON] I
 type CompletedAPI = "submit" :> "completed" :> ReqBody '[JSON] PostCompleted :> Post '[JSON] Int64
   
 type GetCompletedAPI = "get" :> "completed" :> Capture "entry_id" Int :> Get '[JSON] [Bool]
+
+type AllCompletedAPI = "all" :> "completed" :> Get '[JSON] [Int]
   
+type TagsAPI = "submit" :> "tags" :> ReqBody '[JSON] PostTags :> Post '[JSON] Int64
+
+type UntagAPI = "submit" :> "untag" :> ReqBody '[JSON] PostTags :> Post '[JSON] Int64
+
+type EditAPI = "submit" :> "edit" :> ReqBody '[JSON] PostEdit :> Post '[JSON] Int64
+
+type GetEntryAPI = "get" :> "entry" :> Capture "entry_id" Int :> Get '[JSON] [CacheView]
+
+type GetTagsAPI = "get" :> "tags" :> Capture "entry_id" Int :> Get '[JSON] [String]
+
+type RecrawlAPI = "submit" :> "recrawl" :> ReqBody '[JSON] PostRecrawl :> Post '[JSON] Int64
+
+type ArchiveEntryAPI = "archive" :> "entry" :> Capture "entry_id" Int :> Post '[JSON] Int64
+
+type RestoreEntryAPI = "restore" :> "entry" :> Capture "entry_id" Int :> Post '[JSON] Int64
+
+type PurgeEntryAPI = "purge" :> "entry" :> Capture "entry_id" Int :> Post '[JSON] Int64
+
+type AllArchivedAPI = "all" :> "archived" :> Get '[JSON] [CacheView]
+
+type AnnotationAPI = "submit" :> "annotation" :> ReqBody '[JSON] PostAnnotation :> Post '[JSON] Int64
+
+type GetAnnotationsAPI = "get" :> "annotations" :> Capture "entry_id" Int :> Get '[JSON] [Annotation]
+
+type AnnotationCountsAPI = "all" :> "annotations" :> "counts" :> Get '[JSON] [AnnotationCount]
+
+type SearchResultsAPI =
+  "search"
+    :> "results"
+    :> QueryParam "q" String
+    :> QueryParam "limit" Int
+    :> QueryParams "tag" Text
+    :> QueryParams "excludeTag" Text
+    :> QueryParam "site" Text
+    :> QueryParam "completed" Bool
+    :> QueryParam "kind" Text
+    :> QueryParam "startDate" Day
+    :> QueryParam "endDate" Day
+    :> QueryParam "alltags" Bool
+    :> Get '[JSON] [SearchResult]
+
+type CountAPI =
+  "count"
+    :> QueryParam "q" String
+    :> QueryParams "tag" Text
+    :> QueryParams "excludeTag" Text
+    :> QueryParam "site" Text
+    :> QueryParam "completed" Bool
+    :> QueryParam "kind" Text
+    :> QueryParam "startDate" Day
+    :> QueryParam "endDate" Day
+    :> QueryParam "alltags" Bool
+    :> Get '[JSON] Int
+
+type DuplicatesAPI = "duplicates" :> QueryParam "url" String :> Get '[JSON] [CacheView]
+
 type SearchAPI = "search" :> Capture "query" String :> Get '[JSON] [CacheView]
 
+type RelatedAPI = "related" :> Capture "entry_id" Int :> QueryParam "limit" Int :> Get '[JSON] [RelatedEntry]
+
+type CaptureAPI = "frontend" :> "capture" :> Raw
+
+type DetailPageAPI = "frontend" :> "detail" :> Raw
+
+-- | The share target of the manifest
+type AddNotePageAPI = "frontend" :> "addnote" :> Raw
+
 type FrontendAPI = "frontend" :> Raw
 
 type LinkEntryTagsAPI =
@@ -68,6 +146,7 @@ type LinkEntryTagsAPI =
     :> "entry"
     :> "tags"
     :> QueryParams "filter" String
+    :> QueryParams "id" Int
     :> Get '[JSON] [EntryTag]
 
 type HelloTorchAPI =
@@ -85,13 +164,37 @@ type HelloHuggingfaceAPI =
 type CombinedAPI =
   RootAPI
     :<|> AllTagsAPI
+    :<|> AllTagCountsAPI
+    :<|> TagRenameAPI
+    :<|> TagDeleteAPI
     :<|> AllEntriesAPI
     :<|> AllCacheAPI
     :<|> AllTimestampsAPI
     :<|> EntryAPI 
     :<|> CompletedAPI 
     :<|> GetCompletedAPI 
+    :<|> AllCompletedAPI
+    :<|> TagsAPI
+    :<|> UntagAPI
+    :<|> EditAPI
+    :<|> GetEntryAPI
+    :<|> GetTagsAPI
+    :<|> RecrawlAPI
+    :<|> ArchiveEntryAPI
+    :<|> RestoreEntryAPI
+    :<|> PurgeEntryAPI
+    :<|> AllArchivedAPI
+    :<|> AnnotationAPI
+    :<|> GetAnnotationsAPI
+    :<|> AnnotationCountsAPI
+    :<|> SearchResultsAPI
+    :<|> CountAPI
+    :<|> DuplicatesAPI
     :<|> SearchAPI
+    :<|> RelatedAPI
+    :<|> CaptureAPI
+    :<|> DetailPageAPI
+    :<|> AddNotePageAPI
     :<|> FrontendAPI
     :<|> LinkEntryTagsAPI
     :<|> HelloTorchAPI
@@ -104,13 +207,37 @@ server :: Server CombinedAPI
 server =
   getRoot
     :<|> allTagsH
+    :<|> allTagCountsH
+    :<|> postTagRenameH
+    :<|> postTagDeleteH
     :<|> allEntriesH
     :<|> allCacheH
     :<|> allTimestampsH
     :<|> postNoteH
     :<|> postCompletedH
     :<|> getCompletedH
+    :<|> allCompletedH
+    :<|> postTagsH
+    :<|> postUntagH
+    :<|> postEditH
+    :<|> getEntryH
+    :<|> getTagsH
+    :<|> postRecrawlH
+    :<|> archiveEntryH
+    :<|> restoreEntryH
+    :<|> purgeEntryH
+    :<|> allArchivedH
+    :<|> postAnnotationH
+    :<|> getAnnotationsH
+    :<|> annotationCountsH
+    :<|> searchResultsH
+    :<|> countH
+    :<|> duplicatesH
     :<|> searchH
+    :<|> relatedH
+    :<|> appShellH
+    :<|> appShellH
+    :<|> appShellH
     :<|> frontendH
     :<|> linkEntryTagsH
     :<|> helloTorchH
@@ -134,6 +261,7 @@ mkApp = pure $ serve combinedApi server
 runServer :: IO ()
 runServer = do
   let port = 3000
+  createArchived
   withStdoutLogger $ \aplogger -> do
     let settings =
           setPort port $
@@ -9,13 +9,16 @@
 
 module DB where
 
+import Control.Monad (when)
 import Control.Monad.Reader
 import Control.Monad.IO.Class (liftIO)
 import Data.Aeson (FromJSON, ToJSON)
-import Data.List (intercalate)
-import Data.Maybe (catMaybes)
+import Data.Char (isAlphaNum, isSpace, toLower)
+import Data.List (intercalate, isPrefixOf, nub, sortBy, tails)
+import Data.Ord (comparing, Down(..))
+import Data.Maybe (catMaybes, isJust, listToMaybe)
 import Data.Text (Text, pack, unpack)
-import Data.Time (defaultTimeLocale, formatTime, getZonedTime, Day(..), TimeOfDay(..), UTCTime(..), diffDays, nominalDiffTimeToSeconds)
+import Data.Time (defaultTimeLocale, formatTime, getZonedTime, Day(..), TimeOfDay(..), UTCTime(..), diffDays, diffUTCTime, nominalDiffTimeToSeconds)
 import Data.Time.Clock.POSIX
 import Data.Time.Format (parseTimeM)
 import Data.Time.Calendar (toGregorian)
@@ -25,7 +28,7 @@ import GHC.Generics (Generic)
 import GHC.Int (Int64)
 import OCR
 import SQL
-import System.Directory (copyFile, removeFile)
+import System.Directory (copyFile, doesFileExist, removeFile)
 import System.IO (hPutStrLn, stderr)
 import Date
 import Text.Printf (printf)
@@ -68,6 +71,26 @@ instance FromRow Tag where
 
 instance ToJSON Tag
 
+data TagCount = TagCount
+  { tcTag :: String,
+    tcCount :: Int,
+    tcLatest :: Maybe String -- "date time" of the newest entry with the tag
+  }
+  deriving (Show, Generic)
+
+instance FromRow TagCount where
+  fromRow = TagCount <$> field <*> field <*> field
+
+instance ToJSON TagCount
+
+data PostTagRename = PostTagRename { trFrom :: [String], trTo :: String } deriving (Show, Generic)
+instance ToJSON PostTagRename
+instance FromJSON PostTagRename
+
+data PostTagDelete = PostTagDelete { tdTags :: [String] } deriving (Show, Generic)
+instance ToJSON PostTagDelete
+instance FromJSON PostTagDelete
+
 -- Link entries to tags
 
 data EntryTag = EntryTag
@@ -172,6 +195,48 @@ data PostCompleted = PostCompleted { pcEntryID :: Int, pcState :: Bool} deriving
 instance ToJSON PostCompleted
 instance FromJSON PostCompleted
 
+data PostTags = PostTags { ptEntryID :: Int, ptTags :: [String] } deriving (Show, Generic)
+instance ToJSON PostTags
+instance FromJSON PostTags
+
+data PostRecrawl = PostRecrawl { prEntryID :: Int } deriving (Show, Generic)
+instance ToJSON PostRecrawl
+instance FromJSON PostRecrawl
+
+data PostEdit = PostEdit { peEntryID :: Int, peContent :: String } deriving (Show, Generic)
+instance ToJSON PostEdit
+instance FromJSON PostEdit
+
+-- annotations
+
+data Annotation = Annotation
+  { anEntryID :: Int,
+    anDate :: String,
+    anTime :: String,
+    anContent :: String
+  }
+  deriving (Show, Generic)
+
+instance FromRow Annotation where
+  fromRow = Annotation <$> field <*> field <*> field <*> field
+
+instance ToJSON Annotation
+
+data AnnotationCount = AnnotationCount
+  { acEntryID :: Int,
+    acCount :: Int
+  }
+  deriving (Show, Generic)
+
+instance FromRow AnnotationCount where
+  fromRow = AnnotationCount <$> field <*> field
+
+instance ToJSON AnnotationCount
+
+data PostAnnotation = PostAnnotation { paEntryID :: Int, paContent :: String } deriving (Show, Generic)
+instance ToJSON PostAnnotation
+instance FromJSON PostAnnotation
+
 dbFile = "openmemex.db"
 
 -- Helper functions
@@ -230,36 +295,84 @@ getEntry entryID = do
 
 -- handlers
 
+-- | Gallery filters shared by the cache listing and ranked search
+data EntryFilter = EntryFilter
+  { efTags :: [Text], -- entries must have one of these
+    efAllTags :: Bool, -- or every one of them
+    efExcludeTags :: [Text],
+    efSite :: Maybe Text,
+    efCompleted :: Maybe Bool,
+    efKind :: Maybe Text, -- note | link
+    efStartDay :: Maybe Day,
+    efEndDay :: Maybe Day
+  }
+  deriving (Show)
+
+-- | Single quoted SQL string literal
+sqlString :: String -> String
+sqlString s = "'" ++ concatMap (\c -> if c == '\'' then "''" else [c]) s ++ "'"
+
+-- | WHERE conditions on the cache view for a filter, archived entries are always left out
+filterConds :: EntryFilter -> [SqlCond]
+filterConds EntryFilter {..} =
+  tagConds
+    ++ (tagCond "NOT IN" <$> efExcludeTags)
+    ++ maybe [] (\site -> [siteCond $ unpack site]) efSite
+    ++ completedCond
+    ++ kindCond
+    ++ dateCond ">=" efStartDay
+    ++ dateCond "<=" efEndDay
+    ++ [SqlCond "cache.entry_id NOT IN (SELECT entry_id FROM archived)"]
+  where
+    tagConds
+      | efAllTags = tagCond "IN" <$> efTags
+      | null efTags = []
+      | otherwise = [SqlCond $ "cache.entry_id IN (SELECT entry_id FROM tags WHERE " ++ intercalate " OR " (tagMatch . unpack <$> efTags) ++ ")"]
+    tagCond op tag = SqlCond $ "cache.entry_id " ++ op ++ " (SELECT entry_id FROM tags WHERE " ++ tagMatch (unpack tag) ++ ")"
+    -- "ml/*" stands for ml and every tag under it, namespaced with / or :
+    tagMatch tag = case reverse tag of
+      ('*' : sep : rest) | sep `elem` ['/', ':'] ->
+        let parent = reverse rest
+            likeChildren s = "tag LIKE " ++ sqlString (likeEscape parent ++ [s] ++ "%") ++ " ESCAPE '\\'"
+         in "(tag = " ++ sqlString parent ++ " OR " ++ likeChildren '/' ++ " OR " ++ likeChildren ':' ++ ")"
+      _ -> "tag = " ++ sqlString tag
+    likeEscape = concatMap (\c -> if c `elem` ['%', '_', '\\'] then ['\\', c] else [c])
+    -- the site itself or any subdomain of it
+    siteCond site =
+      SqlCond $ "(" ++ intercalate " OR " [urlCol ++ " LIKE " ++ sqlString p | p <- ["%://" ++ site ++ "/%", "%://" ++ site, "%." ++ site ++ "/%", "%." ++ site]] ++ ")"
+    urlCol = "coalesce(cache_url, cache.content)"
+    completedCond = case efCompleted of
+      Nothing -> []
+      Just True -> [SqlCond "cache.entry_id IN (SELECT entry_id FROM completed)"]
+      Just False -> [SqlCond "cache.entry_id NOT IN (SELECT entry_id FROM completed)"]
+    isLink = "(cache.content LIKE 'http://%' OR cache.content LIKE 'https://%')"
+    kindCond = case efKind of
+      Just "link" -> [SqlCond isLink]
+      Just "note" -> [SqlCond $ "NOT " ++ isLink]
+      _ -> []
+    dateCond op day = case day of
+      Nothing -> []
+      Just t -> let (y, m, d) = toGregorian t in [SqlCond $ printf "date %s \"%.4d-%.2d-%.2d\"" op y m d]
+
 allCache 
   :: Maybe SortBy 
   -> Maybe SortDir 
-  -> [Text] 
+  -> EntryFilter
   -> Maybe Int 
   -> Maybe Bool 
-  -> Maybe Day 
-  -> Maybe Day 
   -> IO [CacheView]
-allCache sortby sortdir filterTags limit hideCompleted startDay endDay = do
-  -- TODO: support hideCompleted
-  conn <- open dbFile
-  let tagCond = case filterTags of
-                [] -> []
-                _ -> [SqlCond ("tag IN ('" ++ (intercalate "','" $ unpack <$> filterTags) ++ "')")]
-  let dateStartCond = case startDay of 
-                      Nothing -> []
-                      Just t -> let (y, m, d) = toGregorian t in [SqlCond $ printf "date >= \"%.4d-%.2d-%.2d\"" y m d]
-  let dateEndCond = case endDay of 
-                      Nothing -> []
-                      Just t -> let (y, m, d) = toGregorian t in [SqlCond $ printf "date <= \"%.4d-%.2d-%.2d\"" y m d]
-  let conditions = tagCond ++ dateStartCond  ++ dateEndCond
+allCache sortby sortdir entryFilter limit hideCompleted = do
+  conn <- open dbFile
+  -- an explicit completed filter wins over hidecompleted
+  let entryFilter' = case (efCompleted entryFilter, hideCompleted) of
+                     (Nothing, Just True) -> entryFilter {efCompleted = Just False}
+                     _ -> entryFilter
   let query =
         defaultQuery
           { sqlSelect = SqlCol <$> ["cache.entry_id", "cache_url", "cache_content_type", "cache_title", "date", "time", "cache_screenshot_file", "cache_thumbnail_file"],
-            sqlFrom = if null filterTags
-                      then SqlFrom "cache"
-                      else SqlFrom $ "tags LEFT JOIN cache ON cache.entry_id=tags.entry_id",
+            sqlFrom = SqlFrom "cache",
             sqlLimit = Just limit',
-            sqlWhere = conditions,
+            sqlWhere = filterConds entryFilter',
             sqlOrder = [SqlOrder "date DESC, time DESC"] -- TODO represent individual termws instead of using a string blob
           }
   let queryString = sql2string query
@@ -273,18 +386,22 @@ allCache sortby sortdir filterTags limit hideCompleted startDay endDay = do
       Nothing -> 50
       Just l -> l
 
-linkEntryTags :: [String] -> IO [EntryTag]
-linkEntryTags filterTags = do
+-- | Tags of every entry, or only of the given entries when there are any
+linkEntryTags :: [String] -> [Int] -> IO [EntryTag]
+linkEntryTags filterTags entryIDs = do
   conn <- open dbFile
+  -- joining from tags, a left join from entries gave NULL tags for untagged entries
   let query =
-        if filterTags == []
-          then -- TODO - why does this return a runtime error for the empty case
-          -- ConversionFailed {errSQLType = "NULL", errHaskellType = "[Char]", errMessage = "expecting SQLText column type"}
-            "SELECT entries.entry_id, tag FROM entries LEFT JOIN tags on entries.entry_id=tags.entry_id"
-          else "SELECT entries.entry_id, tag FROM entries LEFT JOIN tags on entries.entry_id=tags.entry_id WHERE tag IN " ++ filterList
-  query_ conn (Query . pack $ query)
+        "SELECT tags.entry_id, tag FROM tags JOIN entries ON entries.entry_id=tags.entry_id "
+          ++ "WHERE tags.entry_id NOT IN (SELECT entry_id FROM archived)"
+          ++ (if null filterTags then "" else " AND tag IN " ++ filterList)
+          ++ (if null entryIDs then "" else " AND tags.entry_id IN " ++ idList)
+  r <- query_ conn (Query . pack $ query)
+  close conn
+  pure r
   where
-    filterList = "(" ++ intercalate "," filterTags ++ ")"
+    filterList = "(" ++ intercalate "," (sqlString <$> filterTags) ++ ")"
+    idList = "(" ++ intercalate "," (show <$> entryIDs) ++ ")"
 
 crawlerOutput2cache :: [(Entry, String, Maybe WebPage)] -> [CacheEntry]
 crawlerOutput2cache out =
@@ -417,6 +534,17 @@ appendCache cacheEntries = do
     cacheEntries
   close conn
 
+-- | Drop an entry's rows from the current cache table, e.g. before it is crawled again
+removeCache :: Int -> IO ()
+removeCache entryID = do
+  conn <- open dbFile
+  r <- query_ conn $ Query "SELECT table_name FROM cache_meta ORDER BY cache_date, cache_time DESC LIMIT 1;"
+  case r of
+    [] -> pure ()
+    (CurrTable tableName : _) ->
+      executeNamed conn (Query . pack $ "DELETE FROM " ++ tableName ++ " WHERE entry_id = :entryID") [":entryID" := entryID]
+  close conn
+
 writeCache :: [CacheEntry] -> IO ()
 writeCache cacheEntries = do
   now <- getZonedTime
@@ -500,11 +628,46 @@ wipeCache = do
   bracketExecute' "DROP VIEW IF EXISTS cache"
   bracketExecute' "CREATE TABLE cache_meta (cache_table_id INTEGER PRIMARY KEY AUTOINCREMENT, table_name TEXT, cache_date TEXT, cache_time TEXT);"
 
+-- | Rename a tag on every entry, entries that already had the new tag keep a single copy
 replaceTag :: String -> String -> IO ()
-replaceTag fromTag toTag = do
+replaceTag fromTag toTag = mergeTags [fromTag] toTag
+
+-- | Replace several tags with one, e.g. to fold spelling variants together
+mergeTags :: [String] -> String -> IO ()
+mergeTags fromTags toTag = do
   backupDB
-  -- TODO - fill-in
-  pure ()
+  conn <- open dbFile
+  mapM_
+    ( \fromTag ->
+        executeNamed conn "UPDATE tags SET tag = :toTag WHERE tag = :fromTag" [":fromTag" := fromTag, ":toTag" := toTag]
+    )
+    fromTags
+  executeNamed
+    conn
+    "DELETE FROM tags WHERE tag = :toTag AND tag_id NOT IN (SELECT min(tag_id) FROM tags WHERE tag = :toTag GROUP BY entry_id)"
+    [":toTag" := toTag]
+  close conn
+
+-- | Remove tags from every entry
+deleteTags :: [String] -> IO ()
+deleteTags tags = do
+  backupDB
+  conn <- open dbFile
+  mapM_ (\tag -> executeNamed conn "DELETE FROM tags WHERE tag = :tag" [":tag" := tag]) tags
+  close conn
+
+-- | Tags used by at least minCount entries, with their counts
+tagCounts :: Maybe Int -> IO [TagCount]
+tagCounts minCount = do
+  conn <- open dbFile
+  r <- queryNamed conn
+    ( "SELECT tag, count(DISTINCT tags.entry_id), max(entries.date || ' ' || entries.time) "
+        <> "FROM tags LEFT JOIN entries ON entries.entry_id=tags.entry_id "
+        <> "GROUP BY tag HAVING count(DISTINCT tags.entry_id) >= :minCount ORDER BY tag"
+    )
+    [":minCount" := maybe 0 id minCount] :: IO [TagCount]
+  close conn
+  pure r
 
 -- | Get current date and time
 getDateTime = do
@@ -542,6 +705,133 @@ addTag entryID tag = do
   close conn
   pure r
 
+-- | Replace the content of an existing entry, keeping its date
+updateContent :: Int -> String -> IO ()
+updateContent entryID newContent = do
+  conn <- open dbFile
+  executeNamed
+    conn
+    "UPDATE entries SET content = :content WHERE entry_id = :entryID"
+    [":entryID" := entryID, ":content" := newContent]
+  close conn
+
+-- | Tags of a single entry
+getTags :: Int -> IO [String]
+getTags entryID = do
+  conn <- open dbFile
+  r <- queryNamed conn "SELECT tag FROM tags WHERE entry_id = :entryID ORDER BY tag" [":entryID" := entryID] :: IO [[String]]
+  close conn
+  pure $ concat r
+
+-- | Add tags to an existing entry, skipping ones it already has
+addTags :: Int -> [String] -> IO ()
+addTags entryID tags = do
+  existing <- getTags entryID
+  mapM_ (addTag $ fromIntegral entryID) (nub $ filter (`notElem` existing) tags)
+
+-- | Remove tags from an entry, ones it doesn't have are ignored
+removeTags :: Int -> [String] -> IO ()
+removeTags entryID tags = do
+  conn <- open dbFile
+  mapM_
+    ( \tag ->
+        executeNamed
+          conn
+          "DELETE FROM tags WHERE entry_id = :entryID AND tag = :tag"
+          [":entryID" := entryID, ":tag" := tag]
+    )
+    tags
+  close conn
+
+-- | Delete an entry with its tags, completion and annotations
+deleteEntry :: Int -> IO Int64
+deleteEntry entryID = do
+  conn <- open dbFile
+  mapM_
+    ( \table ->
+        executeNamed
+          conn
+          (Query . pack $ "DELETE FROM " ++ table ++ " WHERE entry_id = :entryID")
+          [":entryID" := entryID]
+    )
+    ["tags", "completed", "annotations", "archived", "entries"]
+  close conn
+  pure 0
+
+-- | Archived entries are hidden from the gallery until restored or purged.
+-- Created on startup so databases from before the archive keep working.
+createArchived :: IO ()
+createArchived =
+  bracketExecute' "CREATE TABLE IF NOT EXISTS archived (entry_id INTEGER PRIMARY KEY, archived_date TEXT, archived_time TEXT);"
+
+-- | Move an entry to the archive
+archiveEntry :: Int -> IO Int64
+archiveEntry entryID = do
+  (dt, tm) <- getDateTime
+  conn <- open dbFile
+  executeNamed
+    conn
+    "INSERT OR REPLACE INTO archived (entry_id, archived_date, archived_time) VALUES (:entryID, :date, :time)"
+    [":entryID" := entryID, ":date" := dt, ":time" := tm]
+  close conn
+  pure 0
+
+-- | Bring an entry back from the archive
+restoreEntry :: Int -> IO Int64
+restoreEntry entryID = do
+  conn <- open dbFile
+  executeNamed conn "DELETE FROM archived WHERE entry_id = :entryID" [":entryID" := entryID]
+  close conn
+  pure 0
+
+-- | The cache record of an entry, archived or not, falling back to the entry
+-- itself when it hasn't been crawled yet
+cacheView :: Int -> IO [CacheView]
+cacheView entryID = do
+  conn <- open dbFile
+  cached <- queryNamed conn
+    ( "SELECT cache.entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file "
+        <> "FROM cache WHERE cache.entry_id = :entryID"
+    )
+    [":entryID" := entryID] :: IO [CacheView]
+  r <- if null cached
+    then queryNamed conn
+      "SELECT entry_id, NULL, NULL, content, date, time, NULL, NULL FROM entries WHERE entry_id = :entryID"
+      [":entryID" := entryID] :: IO [CacheView]
+    else pure cached
+  close conn
+  pure r
+
+-- | Archived entries, most recently archived first, including ones archived
+-- before they were crawled
+archivedCache :: IO [CacheView]
+archivedCache = do
+  conn <- open dbFile
+  r <- query_ conn $ Query . pack $
+    "SELECT entries.entry_id, cache_url, cache_content_type, coalesce(cache_title, entries.content), entries.date, entries.time, cache_screenshot_file, cache_thumbnail_file "
+      ++ "FROM archived JOIN entries ON entries.entry_id=archived.entry_id "
+      ++ "LEFT JOIN cache ON cache.entry_id=archived.entry_id "
+      ++ "ORDER BY archived_date DESC, archived_time DESC"
+  close conn
+  pure r
+
+-- | Permanently delete an entry along with its cached page, screenshot and thumbnail
+purgeEntry :: Int -> IO Int64
+purgeEntry entryID = do
+  conn <- open dbFile
+  r <- queryNamed conn "SELECT cache_screenshot_file, cache_thumbnail_file FROM cache WHERE entry_id = :entryID" [":entryID" := entryID] :: IO [(Maybe String, Maybe String)]
+  close conn
+  let recorded = concat [catMaybes [s, t] | (s, t) <- r]
+      files = nub $ recorded ++ ([mkScreenshotFilename, mkThumbnailFilename, mkOCRFilename] <*> [entryID])
+  mapM_
+    (\file -> do
+        exists <- doesFileExist file
+        when exists $ removeFile file
+    )
+    files
+  removeCache entryID
+  deleteEntry entryID
+
 addCompleted :: Int -> IO Int64
 addCompleted entryID = do
   (dt, tm) <- getDateTime
@@ -564,6 +854,42 @@ removeCompleted entryID = do
   close conn
   pure 0
 
+addAnnotation :: Int -> String -> IO Int64
+addAnnotation entryID content = do
+  (dt, tm) <- getDateTime
+  conn <- open dbFile
+  executeNamed
+    conn
+    "INSERT INTO annotations (entry_id, annotation_date, annotation_time, annotation_content) VALUES (:entryID, :date, :time, :content)"
+    [":entryID" := entryID, ":date" := dt, ":time" := tm, ":content" := content]
+  r <- lastInsertRowId conn
+  close conn
+  pure r
+
+-- | Annotations for an entry, oldest first so the last element is the current note
+getAnnotations :: Int -> IO [Annotation]
+getAnnotations entryID = do
+  conn <- open dbFile
+  r <- queryNamed conn "SELECT entry_id, annotation_date, annotation_time, annotation_content FROM annotations WHERE entry_id = :entryID ORDER BY annotation_date, annotation_time, annotation_id" [":entryID" := entryID] :: IO [Annotation]
+  close conn
+  pure r
+
+-- | Number of annotations on each annotated entry
+annotationCounts :: IO [AnnotationCount]
+annotationCounts = do
+  conn <- open dbFile
+  r <- query_ conn "SELECT entry_id, COUNT(*) FROM annotations GROUP BY entry_id" :: IO [AnnotationCount]
+  close conn
+  pure r
+
+-- | Ids of every completed entry
+completedEntries :: IO [Int]
+completedEntries = do
+  conn <- open dbFile
+  r <- query_ conn "SELECT DISTINCT entry_id FROM completed" :: IO [Only Int]
+  close conn
+  pure $ fromOnly <$> r
+
 checkCompleted :: Int -> IO Bool
 checkCompleted entryID = do
   conn <- open dbFile
@@ -584,6 +910,237 @@ search query = do
   print queryString
   query_ conn queryString :: IO [CacheView]
 
+-- | Entries whose url contains a string, matching the url the entry was
+-- submitted with too so entries whose crawl failed are found
+urlMatches :: String -> IO [CacheView]
+urlMatches url = do
+  conn <- open dbFile
+  r <- queryNamed conn
+    ( "SELECT cache.entry_id, coalesce(cache_url, cache.content), cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file "
+        <> "FROM cache WHERE (cache_url LIKE :url OR cache.content LIKE :url) "
+        <> "AND cache.entry_id NOT IN (SELECT entry_id FROM archived) "
+        <> "ORDER BY date DESC, time DESC"
+    )
+    [":url" := ("%" ++ url ++ "%")] :: IO [CacheView]
+  close conn
+  pure r
+
+-- search results
+
+-- | A search hit, with the field that matched best and an excerpt of it
+data SearchResult = SearchResult
+  { srEntry :: CacheView,
+    srField :: String, -- title | url | note | tag | page | ocr
+    srSnippet :: String,
+    srScore :: Double
+  }
+  deriving (Show, Generic)
+
+instance ToJSON SearchResult
+
+-- cache view columns followed by note, page body, ocr text and tags
+data SearchRow = SearchRow CacheView (Maybe String) (Maybe String) (Maybe String) (Maybe String)
+
+instance FromRow SearchRow where
+  fromRow = SearchRow <$> fromRow <*> field <*> field <*> field <*> field
+
+-- | How much a hit in each field counts towards relevance
+fieldWeights :: [(String, Double)]
+fieldWeights = [("title", 5), ("tag", 4), ("url", 3), ("note", 3), ("page", 1), ("ocr", 1)]
+
+countOccurrences :: String -> String -> Int
+countOccurrences needle haystack = length . filter (needle `isPrefixOf`) $ tails haystack
+
+-- | Up to snippetLength characters of text around the first term found
+mkSnippet :: [String] -> String -> String
+mkSnippet terms text =
+  prefix ++ take snippetLength (drop start flat) ++ suffix
+  where
+    snippetLength = 200
+    flat = unwords (words text)
+    lower = map toLower flat
+    hits = [i | (i, rest) <- zip [0 ..] (tails lower), any (`isPrefixOf` rest) terms]
+    start = case hits of
+      [] -> 0
+      (i : _) -> max 0 (i - 60)
+    prefix = if start > 0 then "..." else ""
+    suffix = if start + snippetLength < length flat then "..." else ""
+
+scoreRow :: [String] -> SearchRow -> Maybe SearchResult
+scoreRow terms (SearchRow cv note page ocr tags) =
+  case sortBy (comparing (Down . snd)) scored of
+    ((name, score) : _) | score > 0 ->
+      Just SearchResult
+        { srEntry = cv,
+          srField = name,
+          srSnippet = mkSnippet terms (maybe "" id $ lookup name fields),
+          srScore = sum (snd <$> scored)
+        }
+    _ -> Nothing
+  where
+    fields =
+      [ (name, text)
+        | (name, Just text) <-
+            [ ("title", cvContent cv),
+              ("tag", tags),
+              ("url", cvUrl cv),
+              ("note", note),
+              ("page", page),
+              ("ocr", ocr)
+            ]
+      ]
+    -- repeated hits count, but only a few so long pages don't drown out titles
+    fieldScore text = fromIntegral . sum $ (\t -> min 3 $ countOccurrences t (map toLower text)) <$> terms
+    scored = [(name, maybe 0 id (lookup name fieldWeights) * fieldScore text) | (name, text) <- fields]
+
+-- | Words of a search, keeping "quoted phrases" together
+searchTerms :: String -> [String]
+searchTerms s = case dropWhile isSpace s of
+  "" -> []
+  ('"' : rest) ->
+    let (phrase, rest') = break (== '"') rest
+     in filter (not . null) [phrase] ++ searchTerms (drop 1 rest')
+  rest -> let (term, rest') = break isSpace rest in term : searchTerms rest'
+
+-- | FROM and WHERE of a search for the terms, with the parameters they
+-- need and whether the ocr table is there to read
+searchFrom :: Connection -> [String] -> EntryFilter -> IO (Bool, String, [String])
+searchFrom conn terms entryFilter = do
+  -- the ocr table only exists once ocr has been run
+  ocrTable <- query_ conn "SELECT name FROM sqlite_master WHERE type='table' AND name='ocr'" :: IO [[String]]
+  let hasOCR = not (null ocrTable)
+  let ocrJoin = if hasOCR then "LEFT JOIN ocr ON ocr.entry_id=cache.entry_id " else ""
+  let ocrCond = if hasOCR then " OR ocr.ocr_content LIKE ?" else ""
+  let termCond =
+        "(cache_url LIKE ? OR cache_title LIKE ? OR cache.content LIKE ? OR cache_body LIKE ?" ++ ocrCond
+          ++ " OR cache.entry_id IN (SELECT entry_id FROM tags WHERE tag LIKE ?))"
+  let conds = (termCond <$ terms) ++ (sqlCond <$> filterConds entryFilter)
+  let params = concatMap (\t -> replicate (if hasOCR then 6 else 5) ("%" ++ t ++ "%")) terms
+  pure (hasOCR, "FROM cache " ++ ocrJoin ++ (if null conds then "" else "WHERE " ++ intercalate " AND " conds), params)
+
+-- | Entries matching every term of the query in some field, most relevant first
+searchResults :: String -> EntryFilter -> Maybe Int -> IO [SearchResult]
+searchResults queryText entryFilter limit = do
+  putStrLn $ "Searching results for " ++ queryText
+  conn <- open dbFile
+  (hasOCR, fromWhere, params) <- searchFrom conn terms entryFilter
+  let ocrCol = if hasOCR then "ocr.ocr_content" else "NULL"
+  let queryString =
+        "SELECT cache.entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file, "
+          ++ "cache.content, cache_body, " ++ ocrCol ++ ", "
+          ++ "(SELECT group_concat(tag, ' ') FROM tags WHERE tags.entry_id=cache.entry_id) "
+          ++ fromWhere
+  putStrLn queryString
+  rows <- query conn (Query . pack $ queryString) params :: IO [SearchRow]
+  close conn
+  let results = catMaybes $ scoreRow terms <$> rows
+  -- most relevant first, newest first among equals
+  let ordered = sortBy (comparing (\r -> (Down (srScore r), Down (cvDate $ srEntry r), Down (cvTime $ srEntry r)))) results
+  pure $ take (maybe 100 id limit) ordered
+  where
+    terms = map toLower <$> searchTerms queryText
+
+-- | Number of entries a gallery query matches, without the gallery limit.
+-- Counted by the database with the conditions a search would use.
+entryCount :: String -> EntryFilter -> IO Int
+entryCount queryText entryFilter = do
+  conn <- open dbFile
+  (_, fromWhere, params) <- searchFrom conn terms entryFilter
+  -- an entry with several ocr rows is joined once per row
+  r <- query conn (Query . pack $ "SELECT COUNT(DISTINCT cache.entry_id) " ++ fromWhere) params :: IO [Only Int]
+  close conn
+  pure $ maybe 0 fromOnly (listToMaybe r)
+  where
+    terms = map toLower <$> searchTerms queryText
+
+-- related entries
+
+-- | An entry related to another one, with what they have in common
+data RelatedEntry = RelatedEntry
+  { reEntry :: CacheView,
+    reReasons :: [String], -- tags | site | time | text
+    reScore :: Double
+  }
+  deriving (Show, Generic)
+
+instance ToJSON RelatedEntry
+
+-- | Host of a link without a leading www., Nothing for notes
+urlHost :: String -> Maybe String
+urlHost url
+  | "http://" `isPrefixOf` url || "https://" `isPrefixOf` url =
+      let host = takeWhile (`notElem` ("/?#:" :: String)) . drop 3 . snd $ break (== ':') url
+       in Just $ if "www." `isPrefixOf` host then drop 4 host else host
+  | otherwise = Nothing
+
+-- | Lower cased words of a title, short words carry little meaning
+titleWords :: CacheView -> [String]
+titleWords cv =
+  nub . filter ((> 2) . length) . words $
+    map (\c -> if isAlphaNum c then toLower c else ' ') (maybe "" id $ cvContent cv)
+
+-- | Share of the words two titles have in common
+titleSimilarity :: [String] -> [String] -> Double
+titleSimilarity a b
+  | null a || null b = 0
+  | otherwise = fromIntegral (length $ filter (`elem` b) a) / fromIntegral (length $ nub (a ++ b))
+
+-- | Entries sharing tags, the site, the capture hour or title words with an entry, closest first.
+-- The database picks the candidates, only those are scored here.
+relatedEntries :: Int -> Maybe Int -> IO [RelatedEntry]
+relatedEntries entryID limit = do
+  targets <- cacheView entryID
+  case targets of
+    [] -> pure []
+    (target : _) -> do
+      let site = siteOf target
+          captured = capturedAt target
+          -- a handful of words is enough to find titles worth comparing
+          titleTerms = take 8 $ titleWords target
+          conds =
+            ["cache.entry_id IN (SELECT entry_id FROM tags WHERE tag IN (SELECT tag FROM tags WHERE entry_id = :entryID))"]
+              ++ ["cache_url LIKE :site OR cache_url LIKE :wwwSite" | isJust site]
+              ++ ["abs(strftime('%s', date || ' ' || time) - :captured) < 3600" | isJust captured]
+              ++ ["cache_title LIKE :word" ++ show i | (i, _) <- zip [0 :: Int ..] titleTerms]
+          params =
+            [":entryID" := entryID, ":candidates" := candidateLimit]
+              ++ concat [[":site" := ("%://" ++ host ++ "%"), ":wwwSite" := ("%://www." ++ host ++ "%")] | Just host <- [site]]
+              ++ [":captured" := (floor (utcTimeToPOSIXSeconds t) :: Int) | Just t <- [captured]]
+              ++ [pack (":word" ++ show i) := ("%" ++ w ++ "%") | (i, w) <- zip [0 :: Int ..] titleTerms]
+      conn <- open dbFile
+      candidates <- queryNamed conn
+        ( Query . pack $
+            "SELECT cache.entry_id, cache_url, cache_content_type, cache_title, date, time, cache_screenshot_file, cache_thumbnail_file "
+              ++ "FROM cache WHERE cache.entry_id != :entryID AND cache.entry_id NOT IN (SELECT entry_id FROM archived) "
+              ++ "AND (" ++ intercalate " OR " ((\c -> "(" ++ c ++ ")") <$> conds) ++ ") "
+              ++ "ORDER BY date DESC, time DESC LIMIT :candidates"
+        )
+        params :: IO [CacheView]
+      shared <- queryNamed conn
+        ( "SELECT entry_id, count(DISTINCT tag) FROM tags "
+            <> "WHERE tag IN (SELECT tag FROM tags WHERE entry_id = :entryID) AND entry_id != :entryID "
+            <> "GROUP BY entry_id"
+        )
+        [":entryID" := entryID] :: IO [(Int, Int)]
+      close conn
+      let relate cv =
+            let sharedTags = maybe 0 id $ lookup (cvForeignID cv) shared
+                sameSite = isJust site && site == siteOf cv
+                sameHour = maybe False ((< 3600) . abs) (diffUTCTime <$> capturedAt cv <*> captured)
+                similarity = titleSimilarity (titleWords target) (titleWords cv)
+                reasons = [r | (r, True) <- [("tags", sharedTags > 0), ("site", sameSite), ("time", sameHour), ("text", similarity >= 0.2)]]
+                score = 2 * fromIntegral sharedTags + (if sameSite then 1 else 0) + (if sameHour then 1 else 0) + 4 * similarity
+             in if null reasons then Nothing else Just (RelatedEntry cv reasons score)
+          related = catMaybes $ relate <$> candidates
+      -- closest first, newest first among equals
+      pure . take (maybe 10 id limit) $
+        sortBy (comparing (\r -> (Down (reScore r), Down (cvDate $ reEntry r), Down (cvTime $ reEntry r)))) related
+  where
+    -- newest candidates first, older ones past this many are not scored
+    candidateLimit = 500 :: Int
+    siteOf cv = cvUrl cv >>= urlHost
+    capturedAt cv = parseTimeM True defaultTimeLocale "%Y-%m-%d %H:%M:%S" (cvDate cv ++ " " ++ cvTime cv) :: Maybe UTCTime
+
 wipeTesting :: IO ()
 wipeTesting = do
   putStrLn "removing entries and tags where tags==\"testing\""
@@ -599,11 +1156,12 @@ initDB = do
       copyFile dbFile (dbFile ++ ".backup")
       removeFile dbFile
       conn <- open dbFile
-      dropTables' ["entries", "tags", "cache_meta", "annotations"]
+      dropTables' ["entries", "tags", "cache_meta", "annotations", "archived"]
       bracketExecute' "CREATE TABLE entries (entry_id INTEGER PRIMARY KEY AUTOINCREMENT, date TEXT, time TEXT, content TEXT);"
       bracketExecute' "CREATE TABLE tags (tag_id INTEGER PRIMARY KEY AUTOINCREMENT, entry_id INTEGER, tag TEXT);"
       bracketExecute' "CREATE TABLE cache_meta (cache_table_id INTEGER PRIMARY KEY AUTOINCREMENT, table_name TEXT, cache_date TEXT, cache_time TEXT);"
       bracketExecute' "CREATE TABLE annotations(annotation_id INTEGER PRIMARY KEY AUTOINCREMENT, entry_id INTEGER, annotation_date TEXT, annotation_time TEXT, annotation_content TEXT);"
+      createArchived
       createIndices' [Index "idx_tags_entry_id" "tags" "entry_id" False,
                      Index "idx_entries_time" "entries" "time" False,
                      Index "idx_entries_date" "entries" "date" False,